@@ -0,0 +1,462 @@
+use anyhow::Result;
+use chrono::Utc;
+use hmac::{Hmac, KeyInit, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+use crate::errors::AwsCredentialsError;
+use crate::errors::AwsError;
+use crate::utils::{build_client, SelectText};
+use crate::TranslateResult;
+use crate::TIMEOUT;
+
+const AWS_SERVICE: &str = "translate";
+const AWS_REGION: &str = "us-east-1";
+const AWS_TARGET: &str = "AWSShineFrontendService_20170701.TranslateText";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub struct AwsResponse {
+    pub translated_text: String,
+    pub source_language_code: Option<String>,
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("hmac accepts any key length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Signature version 4, returns the `Authorization` header.
+/// The headers must contain `host` and `x-amz-date` and all of them are signed
+pub fn sign_v4(
+    method: &str,
+    path: &str,
+    headers: &[(&str, &str)],
+    body: &[u8],
+    credentials: &AwsCredentials,
+    region: &str,
+    service: &str,
+) -> String {
+    let mut headers: Vec<(String, String)> = headers
+        .iter()
+        .map(|(k, v)| (k.to_lowercase(), v.trim().to_string()))
+        .collect();
+    headers.sort();
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(k, v)| format!("{}:{}\n", k, v))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(k, _)| k.as_str())
+        .collect::<Vec<&str>>()
+        .join(";");
+    // the query is always empty for the json apis
+    let canonical_request = format!(
+        "{}\n{}\n\n{}\n{}\n{}",
+        method,
+        path,
+        canonical_headers,
+        signed_headers,
+        hex(&Sha256::digest(body))
+    );
+
+    let amz_date = headers
+        .iter()
+        .find(|(k, _)| k == "x-amz-date")
+        .map(|(_, v)| v.as_str())
+        .unwrap_or("");
+    let date = amz_date.get(..8).unwrap_or("");
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+    let key = format!("AWS4{}", credentials.secret_access_key);
+    let key = hmac_sha256(key.as_bytes(), date);
+    let key = hmac_sha256(&key, region);
+    let key = hmac_sha256(&key, service);
+    let key = hmac_sha256(&key, "aws4_request");
+    let signature = hex(&hmac_sha256(&key, &string_to_sign));
+
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        credentials.access_key_id, scope, signed_headers, signature
+    )
+}
+
+/// Value of the key in the `[section]` of an ini style aws file
+fn profile_value(text: &str, section: &str, key: &str) -> Option<String> {
+    let mut in_section = false;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('[') && line.ends_with(']') {
+            in_section = line[1..line.len() - 1].trim() == section;
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+        if let Some((k, v)) = line.split_once('=') {
+            if k.trim() == key {
+                return Some(v.trim().to_string());
+            }
+        }
+    }
+    None
+}
+
+pub fn parse_credentials(text: &str, profile: &str) -> Option<AwsCredentials> {
+    Some(AwsCredentials {
+        access_key_id: profile_value(text, profile, "aws_access_key_id")?,
+        secret_access_key: profile_value(text, profile, "aws_secret_access_key")?,
+        session_token: profile_value(text, profile, "aws_session_token"),
+    })
+}
+
+fn aws_file(env_name: &str, name: &str) -> Option<String> {
+    let path = match std::env::var(env_name) {
+        Ok(p) => p,
+        Err(_) => {
+            let home = std::env::var("HOME")
+                .or(std::env::var("USERPROFILE"))
+                .ok()?;
+            format!("{}/.aws/{}", home, name)
+        }
+    };
+    std::fs::read_to_string(path).ok()
+}
+
+fn profile_name() -> String {
+    std::env::var("AWS_PROFILE").unwrap_or("default".to_string())
+}
+
+/// The standard environment variables first, then the shared credentials file
+pub fn load_credentials() -> Result<AwsCredentials> {
+    if let (Ok(id), Ok(secret)) = (
+        std::env::var("AWS_ACCESS_KEY_ID"),
+        std::env::var("AWS_SECRET_ACCESS_KEY"),
+    ) {
+        return Ok(AwsCredentials {
+            access_key_id: id,
+            secret_access_key: secret,
+            session_token: std::env::var("AWS_SESSION_TOKEN").ok(),
+        });
+    }
+    aws_file("AWS_SHARED_CREDENTIALS_FILE", "credentials")
+        .and_then(|text| parse_credentials(&text, &profile_name()))
+        .ok_or(AwsCredentialsError.into())
+}
+
+/// `--region`, the environment variables, the config file and then us-east-1
+fn resolve_region(region: &str) -> String {
+    if region != "null" && !region.is_empty() {
+        return region.to_string();
+    }
+    if let Ok(r) = std::env::var("AWS_REGION").or(std::env::var("AWS_DEFAULT_REGION")) {
+        return r;
+    }
+    // the profiles of the config file are named `[profile name]` except the default one
+    let profile = match profile_name().as_str() {
+        "default" => "default".to_string(),
+        p => format!("profile {}", p),
+    };
+    aws_file("AWS_CONFIG_FILE", "config")
+        .and_then(|text| profile_value(&text, &profile, "region"))
+        .unwrap_or(AWS_REGION.to_string())
+}
+
+/// The options of the aws provider
+#[derive(Debug, Clone)]
+pub struct AwsOptions<'a> {
+    pub region: &'a str,
+    pub endpoint: &'a str,
+    /// Comma separated names of the custom terminologies
+    pub terminology: &'a str,
+    /// formal or informal
+    pub formality: &'a str,
+}
+
+async fn request(
+    sl: &str,
+    tl: &str,
+    text: &str,
+    proxy_str: &str,
+    credentials: &AwsCredentials,
+    options: &AwsOptions<'_>,
+) -> Result<AwsResponse> {
+    let region = resolve_region(options.region);
+    let url = match options.endpoint {
+        "null" => format!("https://translate.{}.amazonaws.com/", region),
+        _ => options.endpoint.to_string(),
+    };
+    let url = reqwest::Url::parse(&url)?;
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or(""), port),
+        None => url.host_str().unwrap_or("").to_string(),
+    };
+
+    let mut body = json!({
+        "Text": text,
+        "SourceLanguageCode": sl,
+        "TargetLanguageCode": tl,
+    });
+    if options.terminology != "null" && !options.terminology.is_empty() {
+        let names: Vec<&str> = options.terminology.split(',').map(|n| n.trim()).collect();
+        body["TerminologyNames"] = json!(names);
+    }
+    if options.formality != "null" && !options.formality.is_empty() {
+        body["Settings"] = json!({ "Formality": options.formality.to_uppercase() });
+    }
+    let body = body.to_string();
+
+    let amz_date = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut headers = vec![
+        ("content-type", "application/x-amz-json-1.1"),
+        ("host", host.as_str()),
+        ("x-amz-date", amz_date.as_str()),
+        ("x-amz-target", AWS_TARGET),
+    ];
+    if let Some(token) = &credentials.session_token {
+        headers.push(("x-amz-security-token", token));
+    }
+    let authorization = sign_v4(
+        "POST",
+        url.path(),
+        &headers,
+        body.as_bytes(),
+        credentials,
+        &region,
+        AWS_SERVICE,
+    );
+
+    let client = build_client(proxy_str);
+    let mut request = client.post(url.clone());
+    // reqwest sets the host header from the url
+    for (k, v) in headers.iter().filter(|(k, _)| *k != "host") {
+        request = request.header(*k, *v);
+    }
+    let res = request
+        .header("authorization", authorization)
+        .body(body)
+        .timeout(Duration::from_secs(TIMEOUT))
+        .send()
+        .await?;
+    if !res.status().is_success() {
+        // {"__type": "com.amazonaws...#UnsupportedLanguagePairException", "message": "..."}
+        let status = res.status().as_u16();
+        let message = match res.json::<serde_json::Value>().await {
+            Ok(v) => {
+                let kind = v["__type"].as_str().unwrap_or("");
+                let kind = kind.rsplit('#').next().unwrap_or(kind);
+                let message = v["message"]
+                    .as_str()
+                    .or(v["Message"].as_str())
+                    .unwrap_or("unknown error");
+                match kind {
+                    "" => message.to_string(),
+                    _ => format!("{}: {}", kind, message),
+                }
+            }
+            Err(_) => "unknown error".to_string(),
+        };
+        return Err(AwsError(format!("{} ({})", message, status)).into());
+    }
+    Ok(res.json::<AwsResponse>().await?)
+}
+
+pub async fn translate_aws(
+    sl: &str, // source language
+    tl: &str, // target language
+    content: &str,
+    proxy_str: &str,
+    options: &AwsOptions<'_>,
+) -> Result<Vec<TranslateResult>> {
+    let credentials = load_credentials()?;
+    let content = SelectText::unescape(content);
+    let res = request(sl, tl, &content, proxy_str, &credentials, options).await?;
+    let item = TranslateResult {
+        orig: content,
+        trans: res.translated_text,
+        alter: Vec::new(),
+        dict: None,
+        romanization: None,
+        correction: None,
+        detected_lang: None,
+    };
+    Ok(vec![item])
+}
+
+pub async fn translate_batch_aws(
+    sl: &str, // source language
+    tl: &str, // target language
+    texts: &[String],
+    proxy_str: &str,
+    options: &AwsOptions<'_>,
+) -> Result<Vec<String>> {
+    let credentials = load_credentials()?;
+    let mut result_vec = Vec::new();
+    for t in texts {
+        // aws rejects an empty text
+        if t.trim().is_empty() {
+            result_vec.push(String::new());
+            continue;
+        }
+        let res = request(sl, tl, t, proxy_str, &credentials, options).await?;
+        result_vec.push(res.translated_text);
+    }
+    Ok(result_vec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::HttpResponse;
+    use crate::test_support::mock_server;
+
+    fn example_credentials() -> AwsCredentials {
+        AwsCredentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: None,
+        }
+    }
+
+    #[test]
+    fn test_sign_v4() {
+        // get-vanilla and post-vanilla of the aws signature v4 test suite
+        let headers = [
+            ("Host", "example.amazonaws.com"),
+            ("X-Amz-Date", "20150830T123600Z"),
+        ];
+        let credentials = example_credentials();
+        let auth = sign_v4(
+            "GET",
+            "/",
+            &headers,
+            b"",
+            &credentials,
+            "us-east-1",
+            "service",
+        );
+        assert_eq!(auth, "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, SignedHeaders=host;x-amz-date, Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31");
+        let auth = sign_v4(
+            "POST",
+            "/",
+            &headers,
+            b"",
+            &credentials,
+            "us-east-1",
+            "service",
+        );
+        assert!(auth.ends_with(
+            "Signature=5da7c1a2acd57cee7505fc6676e4e544621c30862966e37dddb68e92efbe5d6b"
+        ));
+    }
+
+    #[test]
+    fn test_parse_credentials() {
+        let text = "[default]\naws_access_key_id = AKID1\naws_secret_access_key = secret1\n\n[work]\naws_access_key_id=AKID2\naws_secret_access_key=secret2\naws_session_token=token2\n";
+        let c = parse_credentials(text, "work").unwrap();
+        assert_eq!(c.access_key_id, "AKID2");
+        assert_eq!(c.session_token.as_deref(), Some("token2"));
+        let c = parse_credentials(text, "default").unwrap();
+        assert_eq!(c.secret_access_key, "secret1");
+        assert_eq!(c.session_token, None);
+        assert!(parse_credentials(text, "missing").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_translate_aws() {
+        let url = mock_server(|request| {
+            let names = [
+                "content-type",
+                "host",
+                "x-amz-date",
+                "x-amz-target",
+                "x-amz-security-token",
+            ];
+            let headers: Vec<(&str, &str)> = names
+                .iter()
+                .filter_map(|n| request.header(n).map(|v| (*n, v)))
+                .collect();
+            let credentials = AwsCredentials {
+                session_token: request.header("x-amz-security-token").map(String::from),
+                ..example_credentials()
+            };
+            let expected = sign_v4(
+                "POST",
+                request.path(),
+                &headers,
+                &request.body,
+                &credentials,
+                "eu-west-1",
+                "translate",
+            );
+            if request.header("authorization") != Some(expected.as_str()) {
+                return HttpResponse::json(
+                    403,
+                    json!({ "__type": "com.amazon.coral.service#InvalidSignatureException", "message": "The request signature we calculated does not match" }),
+                );
+            }
+            let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            let text = format!(
+                "{}:{}:{}:{}",
+                body["TargetLanguageCode"].as_str().unwrap(),
+                body["TerminologyNames"],
+                body["Settings"]["Formality"],
+                body["Text"].as_str().unwrap()
+            );
+            HttpResponse::json(
+                200,
+                json!({ "TranslatedText": text, "SourceLanguageCode": "en", "TargetLanguageCode": "de" }),
+            )
+        })
+        .await;
+        let options = AwsOptions {
+            region: "eu-west-1",
+            endpoint: &url,
+            terminology: "brand, product",
+            formality: "formal",
+        };
+        let credentials = AwsCredentials {
+            session_token: Some("token".to_string()),
+            ..example_credentials()
+        };
+        let r = request("en", "de", "a&b", "null", &credentials, &options)
+            .await
+            .unwrap();
+        assert_eq!(
+            r.translated_text,
+            "de:[\"brand\",\"product\"]:\"FORMAL\":a&b"
+        );
+
+        let wrong = AwsCredentials {
+            secret_access_key: "wrong".to_string(),
+            ..example_credentials()
+        };
+        let e = request("en", "de", "text", "null", &wrong, &options)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "aws error: InvalidSignatureException: The request signature we calculated does not match (403)"
+        );
+    }
+}
@@ -0,0 +1,304 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::Duration;
+
+use crate::errors::AzureEmptyAuthKeyError;
+use crate::errors::AzureError;
+use crate::utils::{build_client, fliter_short, SelectText};
+use crate::TranslateResult;
+use crate::TIMEOUT;
+
+const AZURE_URL: &str = "https://api.cognitive.microsofttranslator.com";
+/// Azure accepts at most 1000 texts and 50000 chars in one request
+const AZURE_MAX_TEXTS: usize = 1000;
+const AZURE_MAX_BATCH_CHARS: usize = 40000;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AzureTranslation {
+    pub text: String,
+    pub to: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AzureResponse {
+    pub translations: Vec<AzureTranslation>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AzureDictionaryTranslation {
+    pub display_target: String,
+    pub pos_tag: String,
+    pub confidence: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AzureDictionaryResponse {
+    pub display_source: String,
+    pub translations: Vec<AzureDictionaryTranslation>,
+}
+
+async fn request<T: serde::de::DeserializeOwned>(
+    path: &str,
+    params: &[(&str, &str)],
+    texts: &[&str],
+    proxy_str: &str,
+    auth_key: &str,
+    region: &str,
+    endpoint: &str,
+) -> Result<T> {
+    if auth_key == "null" || auth_key.is_empty() {
+        return Err(AzureEmptyAuthKeyError.into());
+    }
+    let endpoint = match endpoint {
+        "null" => AZURE_URL,
+        _ => endpoint.trim_end_matches('/'),
+    };
+    let body: Vec<serde_json::Value> = texts.iter().map(|t| json!({ "Text": t })).collect();
+
+    let client = build_client(proxy_str);
+    let mut request = client
+        .post(format!("{}{}", endpoint, path))
+        .query(&[("api-version", "3.0")])
+        .query(params)
+        .header("Ocp-Apim-Subscription-Key", auth_key);
+    // the global resource does not need a region
+    if region != "null" && !region.is_empty() {
+        request = request.header("Ocp-Apim-Subscription-Region", region);
+    }
+    let res = request
+        .json(&body)
+        .timeout(Duration::from_secs(TIMEOUT))
+        .send()
+        .await?;
+    if !res.status().is_success() {
+        // {"error": {"code": 401000, "message": "..."}}
+        let status = res.status().as_u16();
+        let message = match res.json::<serde_json::Value>().await {
+            Ok(v) => v["error"]["message"]
+                .as_str()
+                .unwrap_or("unknown error")
+                .to_string(),
+            Err(_) => "unknown error".to_string(),
+        };
+        return Err(AzureError(format!("{} ({})", message, status)).into());
+    }
+    Ok(res.json::<T>().await?)
+}
+
+/// Azure detects the source language when there is no `from`
+fn lang_params<'a>(sl: &'a str, tl: &'a str) -> Vec<(&'static str, &'a str)> {
+    match sl {
+        "auto" => vec![("to", tl)],
+        _ => vec![("from", sl), ("to", tl)],
+    }
+}
+
+/// Alternative translations of a single word, sorted by the confidence
+async fn dictionary_lookup(
+    sl: &str,
+    tl: &str,
+    word: &str,
+    proxy_str: &str,
+    auth_key: &str,
+    region: &str,
+    endpoint: &str,
+) -> Result<Vec<String>> {
+    let res: Vec<AzureDictionaryResponse> = request(
+        "/dictionary/lookup",
+        &[("from", sl), ("to", tl)],
+        &[word],
+        proxy_str,
+        auth_key,
+        region,
+        endpoint,
+    )
+    .await?;
+    let mut alter = Vec::new();
+    if let Some(r) = res.into_iter().next() {
+        for t in r.translations {
+            alter.push(format!(
+                "{} ({})",
+                t.display_target,
+                t.pos_tag.to_lowercase()
+            ));
+        }
+    }
+    Ok(alter)
+}
+
+pub async fn translate_azure(
+    sl: &str, // source language
+    tl: &str, // target language
+    content: &str,
+    proxy_str: &str,
+    auth_key: &str,
+    region: &str,
+    endpoint: &str,
+) -> Result<Vec<TranslateResult>> {
+    let content = SelectText::unescape(content);
+    let res: Vec<AzureResponse> = request(
+        "/translate",
+        &lang_params(sl, tl),
+        &[&content],
+        proxy_str,
+        auth_key,
+        region,
+        endpoint,
+    )
+    .await?;
+    let trans = match res.into_iter().next() {
+        Some(r) => r
+            .translations
+            .into_iter()
+            .map(|t| t.text)
+            .collect::<Vec<String>>()
+            .concat(),
+        None => String::new(),
+    };
+
+    // like the google short word path, a single word gets the dictionary alternatives,
+    // the dictionary needs the source language
+    let alter = match content.contains(' ') || sl == "auto" {
+        true => Vec::new(),
+        false => {
+            let word = fliter_short(&content);
+            dictionary_lookup(sl, tl, &word, proxy_str, auth_key, region, endpoint)
+                .await
+                .unwrap_or_default()
+        }
+    };
+    let item = TranslateResult {
+        orig: content,
+        trans,
+        alter,
+        dict: None,
+        romanization: None,
+        correction: None,
+        detected_lang: None,
+    };
+    Ok(vec![item])
+}
+
+pub async fn translate_batch_azure(
+    sl: &str, // source language
+    tl: &str, // target language
+    texts: &[String],
+    proxy_str: &str,
+    auth_key: &str,
+    region: &str,
+    endpoint: &str,
+) -> Result<Vec<String>> {
+    let mut result_vec = Vec::new();
+    let mut start = 0;
+    while start < texts.len() {
+        let mut end = start;
+        let mut batch_chars = 0;
+        while end < texts.len() && end - start < AZURE_MAX_TEXTS {
+            batch_chars += texts[end].chars().count();
+            if end > start && batch_chars > AZURE_MAX_BATCH_CHARS {
+                break;
+            }
+            end += 1;
+        }
+        let batch: Vec<&str> = texts[start..end].iter().map(|t| t.as_str()).collect();
+        let res: Vec<AzureResponse> = request(
+            "/translate",
+            &lang_params(sl, tl),
+            &batch,
+            proxy_str,
+            auth_key,
+            region,
+            endpoint,
+        )
+        .await?;
+        for r in res {
+            let trans: Vec<String> = r.translations.into_iter().map(|t| t.text).collect();
+            result_vec.push(trans.concat());
+        }
+        start = end;
+    }
+    Ok(result_vec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::HttpResponse;
+    use crate::test_support::mock_server;
+
+    async fn azure_server() -> String {
+        mock_server(|request| {
+            if request.header("Ocp-Apim-Subscription-Key") != Some("key")
+                || request.header("Ocp-Apim-Subscription-Region") != Some("westeurope")
+            {
+                return HttpResponse::json(
+                    401,
+                    json!({ "error": { "code": 401000, "message": "invalid subscription key" } }),
+                );
+            }
+            assert_eq!(request.param("api-version"), Some("3.0"));
+            let to = request.param("to").unwrap().to_string();
+            let body: Vec<serde_json::Value> = serde_json::from_slice(&request.body).unwrap();
+            match request.path() {
+                "/translate" => {
+                    assert_ne!(request.param("from"), Some("auto"));
+                    let res: Vec<serde_json::Value> = body
+                        .iter()
+                        .map(|t| {
+                            let text = format!("{}:{}", to, t["Text"].as_str().unwrap());
+                            json!({ "translations": [{ "text": text, "to": to }] })
+                        })
+                        .collect();
+                    HttpResponse::json(200, json!(res))
+                }
+                "/dictionary/lookup" => HttpResponse::json(
+                    200,
+                    json!([{
+                        "normalizedSource": "fly",
+                        "displaySource": "fly",
+                        "translations": [
+                            { "normalizedTarget": "volar", "displayTarget": "volar", "posTag": "VERB", "confidence": 0.4, "prefixWord": "", "backTranslations": [] },
+                            { "normalizedTarget": "mosca", "displayTarget": "mosca", "posTag": "NOUN", "confidence": 0.2, "prefixWord": "", "backTranslations": [] }
+                        ]
+                    }]),
+                ),
+                _ => HttpResponse::json(404, json!({})),
+            }
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_translate_azure() {
+        let url = azure_server().await;
+        let r = translate_azure("en", "es", "fly", "null", "key", "westeurope", &url)
+            .await
+            .unwrap();
+        assert_eq!(r[0].trans, "es:fly");
+        assert_eq!(r[0].alter, vec!["volar (verb)", "mosca (noun)"]);
+
+        let r = translate_azure("en", "es", "fly away", "null", "key", "westeurope", &url)
+            .await
+            .unwrap();
+        assert!(r[0].alter.is_empty());
+        let r = translate_azure("auto", "es", "fly", "null", "key", "westeurope", &url)
+            .await
+            .unwrap();
+        assert_eq!(r[0].trans, "es:fly");
+        assert!(r[0].alter.is_empty());
+
+        let texts = vec!["a".to_string(), "b".to_string()];
+        let r = translate_batch_azure("en", "es", &texts, "null", "key", "westeurope", &url)
+            .await
+            .unwrap();
+        assert_eq!(r, vec!["es:a", "es:b"]);
+
+        let e = translate_azure("en", "es", "fly", "null", "wrong", "westeurope", &url)
+            .await
+            .unwrap_err();
+        assert_eq!(e.to_string(), "azure error: invalid subscription key (401)");
+    }
+}
@@ -0,0 +1,287 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use std::time::SystemTime;
+
+use crate::errors::BaiduAuthError;
+use crate::errors::BaiduBalanceError;
+use crate::errors::BaiduEmptyAuthKeyError;
+use crate::errors::BaiduError;
+use crate::errors::BaiduRateLimitError;
+use crate::utils::{build_client, SelectText};
+use crate::TranslateResult;
+use crate::TIMEOUT;
+
+const BAIDU_URL: &str = "https://fanyi-api.baidu.com/api/trans/vip/translate";
+/// Baidu limits the query to 6000 bytes
+const BAIDU_MAX_BATCH_BYTES: usize = 5000;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BaiduTransResult {
+    pub src: String,
+    pub dst: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BaiduResponse {
+    #[serde(default)]
+    pub trans_result: Vec<BaiduTransResult>,
+    /// The code is a string in most responses but a number in some of them
+    pub error_code: Option<serde_json::Value>,
+    pub error_msg: Option<String>,
+}
+
+/// `sign = md5(appid + q + salt + secret)`, the q is not encoded
+pub fn sign(appid: &str, q: &str, salt: &str, secret: &str) -> String {
+    format!(
+        "{:x}",
+        md5::compute(format!("{}{}{}{}", appid, q, salt, secret))
+    )
+}
+
+fn salt() -> String {
+    let millis = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    (millis % 1_000_000_000).to_string()
+}
+
+/// Map the error code of baidu to the error types
+fn check_error(code: &serde_json::Value, msg: &str) -> Result<()> {
+    let code = match code {
+        serde_json::Value::String(c) => c.clone(),
+        c => c.to_string(),
+    };
+    match code.as_str() {
+        "52000" => Ok(()),
+        "52003" | "54001" | "90107" => Err(BaiduAuthError.into()),
+        "54003" | "54005" => Err(BaiduRateLimitError.into()),
+        "54004" => Err(BaiduBalanceError.into()),
+        _ => Err(BaiduError(format!("{} ({})", msg, code)).into()),
+    }
+}
+
+async fn request(
+    sl: &str,
+    tl: &str,
+    q: &str,
+    proxy_str: &str,
+    appid: &str,
+    secret: &str,
+    endpoint: &str,
+) -> Result<Vec<BaiduTransResult>> {
+    if appid == "null" || secret == "null" || appid.is_empty() || secret.is_empty() {
+        return Err(BaiduEmptyAuthKeyError.into());
+    }
+    let endpoint = match endpoint {
+        "null" => BAIDU_URL,
+        _ => endpoint,
+    };
+    let salt = salt();
+    let sign = sign(appid, q, &salt, secret);
+    let params = [
+        ("q", q),
+        ("from", sl),
+        ("to", tl),
+        ("appid", appid),
+        ("salt", &salt),
+        ("sign", &sign),
+    ];
+
+    let client = build_client(proxy_str);
+    let res = client
+        .post(endpoint)
+        .form(&params)
+        .timeout(Duration::from_secs(TIMEOUT))
+        .send()
+        .await?
+        .json::<BaiduResponse>()
+        .await?;
+    if let Some(code) = &res.error_code {
+        check_error(code, res.error_msg.as_deref().unwrap_or("unknown error"))?;
+    }
+    Ok(res.trans_result)
+}
+
+pub async fn translate_baidu(
+    sl: &str, // source language
+    tl: &str, // target language
+    content: &str,
+    proxy_str: &str,
+    appid: &str,
+    secret: &str,
+    endpoint: &str,
+) -> Result<Vec<TranslateResult>> {
+    let content = SelectText::unescape(content);
+    let res = request(sl, tl, &content, proxy_str, appid, secret, endpoint).await?;
+    // every line of the query is translated separately
+    let trans: Vec<String> = res.into_iter().map(|r| r.dst).collect();
+    let item = TranslateResult {
+        orig: content,
+        trans: trans.join("\n"),
+        alter: Vec::new(),
+        dict: None,
+        romanization: None,
+        correction: None,
+        detected_lang: None,
+    };
+    Ok(vec![item])
+}
+
+pub async fn translate_batch_baidu(
+    sl: &str, // source language
+    tl: &str, // target language
+    texts: &[String],
+    proxy_str: &str,
+    appid: &str,
+    secret: &str,
+    endpoint: &str,
+) -> Result<Vec<String>> {
+    let mut result_vec = Vec::new();
+    let mut start = 0;
+    while start < texts.len() {
+        // baidu rejects an empty query
+        if texts[start].trim().is_empty() {
+            result_vec.push(String::new());
+            start += 1;
+            continue;
+        }
+        // the texts are joined by lines, a text with a line break goes alone
+        let mut end = start + 1;
+        let mut batch_bytes = texts[start].len();
+        while end < texts.len() && !texts[start].contains('\n') && !texts[end].contains('\n') {
+            batch_bytes += texts[end].len() + 1;
+            if batch_bytes > BAIDU_MAX_BATCH_BYTES {
+                break;
+            }
+            end += 1;
+        }
+        let q = texts[start..end].join("\n");
+        let res = request(sl, tl, &q, proxy_str, appid, secret, endpoint).await?;
+        let trans: Vec<String> = res.into_iter().map(|r| r.dst).collect();
+        if end - start == 1 {
+            result_vec.push(trans.join("\n"));
+        } else if trans.len() == end - start {
+            result_vec.extend(trans);
+        } else {
+            // baidu skips the empty lines, translate one by one to keep the order
+            for t in &texts[start..end] {
+                if t.trim().is_empty() {
+                    result_vec.push(String::new());
+                    continue;
+                }
+                let res = request(sl, tl, t, proxy_str, appid, secret, endpoint).await?;
+                let trans: Vec<String> = res.into_iter().map(|r| r.dst).collect();
+                result_vec.push(trans.join("\n"));
+            }
+        }
+        start = end;
+    }
+    Ok(result_vec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::HttpResponse;
+    use crate::test_support::mock_server;
+    use serde_json::json;
+
+    #[test]
+    fn test_sign() {
+        // the example of the baidu api document
+        assert_eq!(
+            sign("2015063000000001", "apple", "1435660288", "12345678"),
+            "f89f9594663708c1605f3d736d01d2d4"
+        );
+        assert_eq!(
+            sign("20240101000000001", "你好\n世界", "42", "secret"),
+            "5992138037d7793f14ab1681f36a0288"
+        );
+    }
+
+    async fn baidu_server() -> String {
+        mock_server(|request| {
+            let param = |name: &str| request.form_param(name);
+            let q = param("q");
+            let expected = sign(&param("appid"), &q, &param("salt"), "secret");
+            if param("sign") != expected {
+                return HttpResponse::json(
+                    200,
+                    json!({ "error_code": "54001", "error_msg": "Invalid Sign" }),
+                );
+            }
+            if q == "limit" {
+                return HttpResponse::json(
+                    200,
+                    json!({ "error_code": 54003, "error_msg": "Invalid Access Limit" }),
+                );
+            }
+            if q == "closed" {
+                return HttpResponse::json(
+                    200,
+                    json!({ "error_code": "58002", "error_msg": "Service Currently Unavailable" }),
+                );
+            }
+            let trans: Vec<serde_json::Value> = q
+                .split('\n')
+                .filter(|l| !l.is_empty())
+                .map(|l| json!({ "src": l, "dst": format!("{}:{}", param("to"), l) }))
+                .collect();
+            HttpResponse::json(
+                200,
+                json!({ "from": param("from"), "to": param("to"), "trans_result": trans }),
+            )
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_translate_baidu() {
+        let url = baidu_server().await;
+        let r = translate_baidu("en", "zh", "a%26b\nc", "null", "appid", "secret", &url)
+            .await
+            .unwrap();
+        assert_eq!(r[0].orig, "a&b\nc");
+        assert_eq!(r[0].trans, "zh:a&b\nzh:c");
+
+        let texts = vec![
+            "a".to_string(),
+            "".to_string(),
+            "b".to_string(),
+            "c\nd".to_string(),
+        ];
+        let r = translate_batch_baidu("en", "zh", &texts, "null", "appid", "secret", &url)
+            .await
+            .unwrap();
+        assert_eq!(r, vec!["zh:a", "", "zh:b", "zh:c\nzh:d"]);
+    }
+
+    #[tokio::test]
+    async fn test_error_codes() {
+        let url = baidu_server().await;
+        let e = translate_baidu("en", "zh", "word", "null", "appid", "wrong", &url)
+            .await
+            .unwrap_err();
+        assert!(e.downcast_ref::<BaiduAuthError>().is_some());
+
+        let e = translate_baidu("en", "zh", "limit", "null", "appid", "secret", &url)
+            .await
+            .unwrap_err();
+        assert!(e.downcast_ref::<BaiduRateLimitError>().is_some());
+
+        let e = translate_baidu("en", "zh", "closed", "null", "appid", "secret", &url)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "baidu error: Service Currently Unavailable (58002)"
+        );
+
+        let e = translate_baidu("en", "zh", "word", "null", "null", "secret", &url)
+            .await
+            .unwrap_err();
+        assert!(e.downcast_ref::<BaiduEmptyAuthKeyError>().is_some());
+    }
+}
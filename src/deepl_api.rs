@@ -1,13 +1,45 @@
 use anyhow::Result;
-use reqwest;
+use colored::Colorize;
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
 
+use crate::deepl_glossary::resolve_glossary;
+use crate::deepl_languages::{cache_path, load_languages, DeepLLanguage};
+use crate::errors::DeepLAuthError;
+use crate::errors::DeepLBudgetError;
 use crate::errors::DeepLEmptyAuthKeyError;
-use crate::utils::{build_proxy, fliter_long, fliter_short};
+use crate::errors::DeepLError;
+use crate::errors::DeepLOptionError;
+use crate::errors::DeepLQuotaExceededError;
+use crate::errors::DeepLRateLimitError;
+use crate::errors::DeepLServerError;
+use crate::errors::DeepLTooLargeError;
+use crate::errors::UnsupportApiError;
+use crate::utils::{build_client, fliter_long, fliter_short, SelectText};
+use crate::Args;
 use crate::TranslateResult;
 use crate::TIMEOUT;
 
+const DEEPL_FREE_URL: &str = "https://api-free.deepl.com";
+const DEEPL_PRO_URL: &str = "https://api.deepl.com";
+
+/// DeepL accepts at most 50 texts in one request
+const DEEPL_MAX_TEXTS: usize = 50;
+/// The total request size is limited to 128 KiB, keep some room for the other params
+const DEEPL_MAX_BATCH_BYTES: usize = 100 * 1024;
+/// Without the preflight the usage is only fetched for the warning, at most every 10 minutes
+const DEEPL_USAGE_WARN_INTERVAL: u64 = 600;
+/// When the usage of the endpoint was fetched for the warning, `(base_url, time)`
+static USAGE_WARNED: Mutex<Vec<(String, SystemTime)>> = Mutex::new(Vec::new());
+
+const DEEPL_SPLIT_SENTENCES: [&str; 3] = ["0", "1", "nonewlines"];
+const DEEPL_MODEL_TYPES: [&str; 3] = [
+    "latency_optimized",
+    "quality_optimized",
+    "prefer_quality_optimized",
+];
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Translation {
     pub detected_source_language: String,
@@ -19,79 +51,609 @@ pub struct DeepLResponse {
     pub translations: Vec<Translation>,
 }
 
+/// The characters used in the current billing period
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeepLUsage {
+    pub character_count: u64,
+    pub character_limit: u64,
+}
+
+impl DeepLUsage {
+    pub fn percent(&self) -> u64 {
+        match self.character_limit {
+            0 => 100,
+            limit => self.character_count * 100 / limit,
+        }
+    }
+}
+
+/// The options of the deepl provider
+#[derive(Debug, Clone)]
+pub struct DeepLOptions<'a> {
+    /// Replaces the address of the free or the pro api
+    pub endpoint: &'a str,
+    /// Glossary id or name, the name is looked up for the language pair
+    pub glossary: &'a str,
+    /// Own character limit of the billing period, 0 only checks the deepl limit
+    /// when `preflight` is set
+    pub budget: u64,
+    /// Check the usage before sending and refuse a text which does not fit
+    pub preflight: bool,
+    /// Warn when the usage reaches this percent of the limit
+    pub warn_percent: u64,
+    /// more, less, prefer_more, prefer_less or default, formal and informal are the
+    /// same as more and less
+    pub formality: &'a str,
+    /// Text around the selection which helps the translation, it is not translated
+    pub context: &'a str,
+    /// 0, 1 or nonewlines
+    pub split_sentences: &'a str,
+    pub preserve_formatting: bool,
+    /// latency_optimized, quality_optimized or prefer_quality_optimized
+    pub model_type: &'a str,
+    /// text, html or xml, the tags are handled by deepl in the last two
+    pub tag_handling: &'a str,
+    /// Comma separated tags whose content is not translated
+    pub ignore_tags: &'a str,
+}
+
+#[cfg(test)]
+impl<'a> DeepLOptions<'a> {
+    /// The default options with another api address
+    pub fn with_endpoint(endpoint: &'a str) -> DeepLOptions<'a> {
+        DeepLOptions {
+            endpoint,
+            glossary: "null",
+            budget: 0,
+            preflight: false,
+            warn_percent: 0,
+            formality: "null",
+            context: "null",
+            split_sentences: "1",
+            preserve_formatting: false,
+            model_type: "null",
+            tag_handling: "text",
+            ignore_tags: "null",
+        }
+    }
+}
+
+fn is_set(option: &str) -> bool {
+    option != "null" && !option.is_empty()
+}
+
+/// The optional params of `/v2/translate`, the values are checked before sending,
+/// `targets` tells which target languages have the formality option
+pub fn option_params<'a>(
+    tl: &str,
+    options: &DeepLOptions<'a>,
+    targets: &[DeepLLanguage],
+) -> Result<Vec<(&'static str, &'a str)>> {
+    if !DEEPL_SPLIT_SENTENCES.contains(&options.split_sentences) {
+        return Err(DeepLOptionError(format!(
+            "split sentences {} is not one of {}",
+            options.split_sentences,
+            DEEPL_SPLIT_SENTENCES.join(", ")
+        ))
+        .into());
+    }
+    let mut params = vec![("split_sentences", options.split_sentences)];
+    if is_set(options.formality) {
+        let formality = match options.formality {
+            "formal" | "more" => "more",
+            "informal" | "less" => "less",
+            "prefer_more" => "prefer_more",
+            "prefer_less" => "prefer_less",
+            "default" => "default",
+            f => {
+                return Err(DeepLOptionError(format!(
+                    "formality {} is not one of more, less, prefer_more, prefer_less, default",
+                    f
+                ))
+                .into())
+            }
+        };
+        // the prefer_ ones fall back to the default for the other languages
+        let supported = targets
+            .iter()
+            .any(|l| l.supports_formality && l.language.eq_ignore_ascii_case(tl));
+        if matches!(formality, "more" | "less") && !supported {
+            return Err(DeepLOptionError(format!(
+                "{} has no formality, use prefer_{} instead",
+                tl, formality
+            ))
+            .into());
+        }
+        params.push(("formality", formality));
+    }
+    if is_set(options.context) {
+        params.push(("context", options.context));
+    }
+    if options.preserve_formatting {
+        params.push(("preserve_formatting", "1"));
+    }
+    if is_set(options.model_type) {
+        if !DEEPL_MODEL_TYPES.contains(&options.model_type) {
+            return Err(DeepLOptionError(format!(
+                "model type {} is not one of {}",
+                options.model_type,
+                DEEPL_MODEL_TYPES.join(", ")
+            ))
+            .into());
+        }
+        params.push(("model_type", options.model_type));
+    }
+    match options.tag_handling {
+        "html" | "xml" => params.push(("tag_handling", options.tag_handling)),
+        _ if is_set(options.ignore_tags) => {
+            return Err(DeepLOptionError(
+                "ignore tags needs --input-format html or xml".to_string(),
+            )
+            .into())
+        }
+        _ => (),
+    }
+    if is_set(options.ignore_tags) {
+        params.push(("ignore_tags", options.ignore_tags));
+    }
+    Ok(params)
+}
+
+/// Same as `option_params`, the target list is only loaded for the strict formality
+async fn checked_option_params<'a>(
+    client: &reqwest::Client,
+    base_url: &str,
+    auth_key: &str,
+    tl: &str,
+    options: &DeepLOptions<'a>,
+) -> Result<Vec<(&'static str, &'a str)>> {
+    let targets = match options.formality {
+        "formal" | "more" | "informal" | "less" => {
+            let cache = cache_path();
+            load_languages(client, base_url, auth_key, cache.as_deref())
+                .await
+                .target
+        }
+        _ => Vec::new(),
+    };
+    option_params(tl, options, &targets)
+}
+
+/// The address of the api without the `/v2` path, the free keys end with `:fx`
+pub fn base_url<'a>(auth_key: &str, endpoint: &'a str) -> &'a str {
+    match endpoint {
+        "null" if auth_key.ends_with(":fx") => DEEPL_FREE_URL,
+        "null" => DEEPL_PRO_URL,
+        _ => endpoint.trim_end_matches('/'),
+    }
+}
+
+/// The value of the `Authorization` header
+pub fn auth_value(auth_key: &str) -> String {
+    format!("DeepL-Auth-Key {}", auth_key)
+}
+
+/// The subcommands work with both `deepl` and `deeplpro`, the key decides the api
+pub fn check_api(api: &str) -> Result<()> {
+    match api {
+        "deepl" | "deeplpro" => Ok(()),
+        _ => Err(UnsupportApiError.into()),
+    }
+}
+
+pub fn check_auth_key(auth_key: &str) -> Result<()> {
+    match auth_key {
+        "null" | "" => Err(DeepLEmptyAuthKeyError.into()),
+        _ => Ok(()),
+    }
+}
+
+/// The known status codes get their own errors, the others keep the message of the
+/// `{"message": "..."}` body
+pub async fn check_response(res: reqwest::Response) -> Result<reqwest::Response> {
+    if res.status().is_success() {
+        return Ok(res);
+    }
+    let status = res.status().as_u16();
+    match status {
+        403 => return Err(DeepLAuthError.into()),
+        413 => return Err(DeepLTooLargeError.into()),
+        429 => return Err(DeepLRateLimitError.into()),
+        456 => return Err(DeepLQuotaExceededError.into()),
+        500..=599 => return Err(DeepLServerError(status).into()),
+        _ => (),
+    }
+    let message = match res.json::<serde_json::Value>().await {
+        Ok(v) => v["message"].as_str().unwrap_or("unknown error").to_string(),
+        Err(_) => "unknown error".to_string(),
+    };
+    Err(DeepLError(format!("{} ({})", message, status)).into())
+}
+
+pub async fn usage(client: &reqwest::Client, base_url: &str, auth_key: &str) -> Result<DeepLUsage> {
+    let res = client
+        .get(format!("{}/v2/usage", base_url))
+        .header("Authorization", auth_value(auth_key))
+        .timeout(Duration::from_secs(TIMEOUT))
+        .send()
+        .await?;
+    Ok(check_response(res).await?.json::<DeepLUsage>().await?)
+}
+
+/// The characters which can still be sent, the budget lowers the deepl limit
+pub fn remaining_chars(usage: &DeepLUsage, budget: u64) -> u64 {
+    let limit = match budget {
+        0 => usage.character_limit,
+        _ => budget.min(usage.character_limit),
+    };
+    limit.saturating_sub(usage.character_count)
+}
+
+fn warn_usage(usage: &DeepLUsage, warn_percent: u64) {
+    if warn_percent > 0 && usage.percent() >= warn_percent {
+        eprintln!(
+            "{}",
+            format!(
+                "deepl usage at {}% ({} of {} characters)",
+                usage.percent(),
+                usage.character_count,
+                usage.character_limit
+            )
+            .yellow()
+        );
+    }
+}
+
+/// Whether the usage of the endpoint is due for the warning, the time is taken when it is
+fn usage_warn_due(base_url: &str) -> bool {
+    let now = SystemTime::now();
+    let mut warned = USAGE_WARNED.lock().unwrap();
+    match warned.iter_mut().find(|(url, _)| url == base_url) {
+        Some((_, last)) => match now.duration_since(*last) {
+            Ok(d) if d.as_secs() < DEEPL_USAGE_WARN_INTERVAL => false,
+            _ => {
+                *last = now;
+                true
+            }
+        },
+        None => {
+            warned.push((base_url.to_string(), now));
+            true
+        }
+    }
+}
+
+/// Nothing is sent when the texts would go over the remaining characters
+async fn preflight(
+    client: &reqwest::Client,
+    base_url: &str,
+    auth_key: &str,
+    chars: usize,
+    options: &DeepLOptions<'_>,
+) -> Result<()> {
+    if !options.preflight && options.budget == 0 {
+        // only the warning, a failed usage request does not stop the translation
+        if options.warn_percent > 0 && usage_warn_due(base_url) {
+            if let Ok(usage) = usage(client, base_url, auth_key).await {
+                warn_usage(&usage, options.warn_percent);
+            }
+        }
+        return Ok(());
+    }
+    let usage = usage(client, base_url, auth_key).await?;
+    warn_usage(&usage, options.warn_percent);
+    let remaining = remaining_chars(&usage, options.budget);
+    match chars as u64 > remaining {
+        true => Err(DeepLBudgetError {
+            chars: chars as u64,
+            remaining,
+        }
+        .into()),
+        false => Ok(()),
+    }
+}
+
+/// The `usage` subcommand
+pub async fn usage_command(args: &Args) -> Result<()> {
+    check_api(&args.api)?;
+    check_auth_key(&args.auth_key)?;
+    let client = build_client(&args.proxy);
+    let base_url = base_url(&args.auth_key, &args.endpoint);
+    let usage = usage(&client, base_url, &args.auth_key).await?;
+    println!(
+        "{}{}{}{}",
+        "Used ".green(),
+        format!(
+            "{} of {} characters",
+            usage.character_count, usage.character_limit
+        )
+        .green()
+        .bold(),
+        format!(" ({}%), ", usage.percent()).green(),
+        format!("{} left", remaining_chars(&usage, args.deepl_budget))
+            .green()
+            .bold()
+    );
+    warn_usage(&usage, args.deepl_warn_percent);
+    Ok(())
+}
+
 async fn tranlate(
     sl: &str, // source language
     tl: &str, // target language
     content: &str,
     proxy_str: &str,
     auth_key: &str,
-    translate_url: &str,
+    base_url: &str,
+    options: &DeepLOptions<'_>,
 ) -> Result<Vec<TranslateResult>> {
-    let translate_string = fliter_long(content);
-    let translate_string = fliter_short(&translate_string);
-
-    let proxy = build_proxy(proxy_str);
-    let client = match proxy {
-        Some(p) => reqwest::Client::builder()
-            .proxy(p)
-            .build()
-            .expect("proxy client build failed"),
-        _ => reqwest::Client::new(),
+    // the filters drop the brackets of the tags
+    let text = match options.tag_handling {
+        "html" | "xml" => SelectText::unescape(content),
+        _ => SelectText::unescape(&fliter_short(&fliter_long(content))),
     };
 
-    let auth_value = format!("DeepL-Auth-Key {}", auth_key);
-    let body = format!(
-        "text={}&source_lang={}&target_lang={}&split_sentences=1",
-        translate_string, sl, tl
-    );
+    let client = build_client(proxy_str);
+    let option_params = checked_option_params(&client, base_url, auth_key, tl, options).await?;
+    preflight(&client, base_url, auth_key, text.chars().count(), options).await?;
+    let glossary_id =
+        resolve_glossary(&client, base_url, auth_key, sl, tl, options.glossary).await?;
+    let mut params = vec![("text", text.as_str()), ("target_lang", tl)];
+    // deepl detects the language when there is no source
+    if sl != "auto" {
+        params.push(("source_lang", sl));
+    }
+    params.extend(option_params);
+    if let Some(id) = &glossary_id {
+        params.push(("glossary_id", id));
+    }
     let res = client
-        .post(translate_url)
-        .header("Authorization", auth_value)
-        .header("Content-Type", "application/x-www-form-urlencoded")
-        .body(body)
+        .post(format!("{}/v2/translate", base_url))
+        .header("Authorization", auth_value(auth_key))
+        .form(&params)
         .timeout(Duration::from_secs(TIMEOUT))
         .send()
-        .await?
-        .json::<DeepLResponse>()
         .await?;
+    let res = check_response(res).await?.json::<DeepLResponse>().await?;
 
     let mut result_vec = Vec::new();
     let trans = res.translations;
     for t in trans {
         let item = TranslateResult {
             trans: t.text,
-            orig: translate_string.to_string(),
+            orig: text.to_string(),
             alter: Vec::new(),
+            dict: None,
+            romanization: None,
+            correction: None,
+            detected_lang: None,
         };
         result_vec.push(item);
     }
     Ok(result_vec)
 }
 
-pub async fn translate_free(
+async fn tranlate_texts(
     sl: &str, // source language
     tl: &str, // target language
-    content: &str,
+    texts: &[String],
     proxy_str: &str,
     auth_key: &str,
-) -> Result<Vec<TranslateResult>> {
-    if auth_key == "null" || auth_key.len() == 0 {
-        return Err(DeepLEmptyAuthKeyError.into());
+    base_url: &str,
+    options: &DeepLOptions<'_>,
+) -> Result<Vec<String>> {
+    let client = build_client(proxy_str);
+    let option_params = checked_option_params(&client, base_url, auth_key, tl, options).await?;
+    let chars = texts.iter().map(|t| t.chars().count()).sum();
+    preflight(&client, base_url, auth_key, chars, options).await?;
+    let glossary_id =
+        resolve_glossary(&client, base_url, auth_key, sl, tl, options.glossary).await?;
+    let auth_value = auth_value(auth_key);
+
+    let mut result_vec = Vec::new();
+    let mut start = 0;
+    while start < texts.len() {
+        // split the texts into batches which fit the deepl request limits
+        let mut end = start;
+        let mut batch_bytes = 0;
+        while end < texts.len() && end - start < DEEPL_MAX_TEXTS {
+            batch_bytes += texts[end].len();
+            if end > start && batch_bytes > DEEPL_MAX_BATCH_BYTES {
+                break;
+            }
+            end += 1;
+        }
+        let mut params = vec![("target_lang", tl)];
+        if sl != "auto" {
+            params.push(("source_lang", sl));
+        }
+        params.extend(option_params.iter().cloned());
+        if let Some(id) = &glossary_id {
+            params.push(("glossary_id", id));
+        }
+        for t in &texts[start..end] {
+            params.push(("text", t.as_str()));
+        }
+        let res = client
+            .post(format!("{}/v2/translate", base_url))
+            .header("Authorization", &auth_value)
+            .form(&params)
+            .timeout(Duration::from_secs(TIMEOUT))
+            .send()
+            .await?;
+        let res = check_response(res).await?.json::<DeepLResponse>().await?;
+        for t in res.translations {
+            result_vec.push(t.text);
+        }
+        start = end;
     }
-    let translate_url = format!("https://api-free.deepl.com/v2/translate");
-    tranlate(sl, tl, content, proxy_str, auth_key, &translate_url).await
+    Ok(result_vec)
 }
 
-pub async fn translate_pro(
+pub async fn translate_deepl(
     sl: &str, // source language
     tl: &str, // target language
     content: &str,
     proxy_str: &str,
     auth_key: &str,
+    options: &DeepLOptions<'_>,
 ) -> Result<Vec<TranslateResult>> {
-    if auth_key == "null" || auth_key.len() == 0 {
-        return Err(DeepLEmptyAuthKeyError.into());
+    check_auth_key(auth_key)?;
+    let base_url = base_url(auth_key, options.endpoint);
+    tranlate(sl, tl, content, proxy_str, auth_key, base_url, options).await
+}
+
+pub async fn translate_batch_deepl(
+    sl: &str, // source language
+    tl: &str, // target language
+    texts: &[String],
+    proxy_str: &str,
+    auth_key: &str,
+    options: &DeepLOptions<'_>,
+) -> Result<Vec<String>> {
+    check_auth_key(auth_key)?;
+    let base_url = base_url(auth_key, options.endpoint);
+    tranlate_texts(sl, tl, texts, proxy_str, auth_key, base_url, options).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::deepl_server;
+
+    #[test]
+    fn test_base_url() {
+        assert_eq!(base_url("0000:fx", "null"), DEEPL_FREE_URL);
+        assert_eq!(base_url("0000", "null"), DEEPL_PRO_URL);
+        assert_eq!(base_url("0000:fx", "http://127.0.0.1/"), "http://127.0.0.1");
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let url = deepl_server().await;
+        let options = DeepLOptions::with_endpoint(&url);
+        let translate =
+            |text: &'static str| translate_deepl("EN", "DE", text, "null", "key", &options);
+        let e = translate("busy").await.unwrap_err();
+        assert!(e.downcast_ref::<DeepLRateLimitError>().is_some());
+        let e = translate("down").await.unwrap_err();
+        assert_eq!(e.to_string(), "deepl is unavailable (503), try again later");
+        let e = translate("teapot").await.unwrap_err();
+        assert_eq!(e.to_string(), "deepl error: I'm a teapot (418)");
+    }
+
+    #[test]
+    fn test_usage_warn_due() {
+        assert!(usage_warn_due("http://127.0.0.1:1"));
+        assert!(!usage_warn_due("http://127.0.0.1:1"));
+        assert!(usage_warn_due("http://127.0.0.1:2"));
+        // the time is up
+        for (url, last) in USAGE_WARNED.lock().unwrap().iter_mut() {
+            if url == "http://127.0.0.1:1" {
+                *last = SystemTime::now() - Duration::from_secs(DEEPL_USAGE_WARN_INTERVAL);
+            }
+        }
+        assert!(usage_warn_due("http://127.0.0.1:1"));
+    }
+
+    #[test]
+    fn test_remaining_chars() {
+        let usage = DeepLUsage {
+            character_count: 1000,
+            character_limit: 500000,
+        };
+        assert_eq!(usage.percent(), 0);
+        assert_eq!(remaining_chars(&usage, 0), 499000);
+        assert_eq!(remaining_chars(&usage, 1500), 500);
+        assert_eq!(remaining_chars(&usage, 800), 0);
+    }
+
+    #[test]
+    fn test_option_params() {
+        let targets = crate::deepl_languages::bundled().target;
+        let option_params =
+            |tl, options: &DeepLOptions<'static>| option_params(tl, options, &targets);
+        let mut options = DeepLOptions::with_endpoint("null");
+        assert_eq!(
+            option_params("DE", &options).unwrap(),
+            vec![("split_sentences", "1")]
+        );
+        options.formality = "formal";
+        options.context = "a paper about models";
+        options.preserve_formatting = true;
+        options.model_type = "quality_optimized";
+        assert_eq!(
+            option_params("PT-BR", &options).unwrap(),
+            vec![
+                ("split_sentences", "1"),
+                ("formality", "more"),
+                ("context", "a paper about models"),
+                ("preserve_formatting", "1"),
+                ("model_type", "quality_optimized"),
+            ]
+        );
+        let e = option_params("EN-US", &options).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "invalid deepl option: EN-US has no formality, use prefer_more instead"
+        );
+        options.formality = "prefer_more";
+        assert!(option_params("EN-US", &options).is_ok());
+        options.formality = "polite";
+        assert!(option_params("DE", &options).is_err());
+        options.formality = "null";
+        options.split_sentences = "2";
+        assert!(option_params("DE", &options).is_err());
+
+        let mut options = DeepLOptions::with_endpoint("null");
+        options.ignore_tags = "code,pre";
+        assert!(option_params("DE", &options).is_err());
+        options.tag_handling = "xml";
+        assert_eq!(
+            option_params("DE", &options).unwrap()[1..],
+            [("tag_handling", "xml"), ("ignore_tags", "code,pre")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_markup() {
+        let url = deepl_server().await;
+        let mut options = DeepLOptions::with_endpoint(&url);
+        let r = translate_deepl("EN", "DE", "<b>Hi</b>, you.", "null", "key", &options)
+            .await
+            .unwrap();
+        assert_eq!(r[0].trans, "bHi/b you");
+        options.tag_handling = "html";
+        let r = translate_deepl("EN", "DE", "<b>Hi</b>, you.", "null", "key", &options)
+            .await
+            .unwrap();
+        assert_eq!(r[0].trans, "<b>Hi</b>, you.");
+    }
+
+    #[tokio::test]
+    async fn test_budget() {
+        let url = deepl_server().await;
+        let mut options = DeepLOptions::with_endpoint(&url);
+        options.preflight = true;
+        let r = translate_deepl("EN", "DE", "hello", "null", "key", &options)
+            .await
+            .unwrap();
+        assert_eq!(r[0].trans, "hello");
+
+        // 5000 characters are left, the own budget leaves 5
+        options.budget = 495005;
+        let texts = vec!["abc".to_string(), "def".to_string()];
+        let e = translate_batch_deepl("EN", "DE", &texts, "null", "key", &options)
+            .await
+            .unwrap_err();
+        let e = e.downcast_ref::<DeepLBudgetError>().unwrap();
+        assert_eq!((e.chars, e.remaining), (6, 5));
+
+        options.budget = 0;
+        options.preflight = false;
+        let e = translate_deepl("EN", "DE", "over", "null", "key", &options)
+            .await
+            .unwrap_err();
+        assert!(e.downcast_ref::<DeepLQuotaExceededError>().is_some());
     }
-    let translate_url = format!("https://api.deepl.com/v2/translate");
-    tranlate(sl, tl, content, proxy_str, auth_key, &translate_url).await
 }
@@ -0,0 +1,400 @@
+use anyhow::Result;
+use colored::Colorize;
+use reqwest::multipart::{Form, Part};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::deepl_api::{auth_value, base_url, check_api, check_auth_key, check_response};
+use crate::deepl_glossary::resolve_glossary;
+use crate::errors::{DeepLAuthError, DeepLDocumentNotFoundError, DeepLError};
+use crate::utils::build_client;
+use crate::Args;
+use crate::TIMEOUT;
+
+/// The id and the key of an uploaded document, both are needed to get the result
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DocumentHandle {
+    pub document_id: String,
+    pub document_key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DocumentStatus {
+    pub document_id: String,
+    /// queued, translating, done or error
+    pub status: String,
+    pub seconds_remaining: Option<u64>,
+    pub billed_characters: Option<u64>,
+    pub error_message: Option<String>,
+}
+
+/// The state file, a job of another input or target language is not resumed
+#[derive(Serialize, Deserialize, Debug)]
+struct DocumentState {
+    input: PathBuf,
+    target_lang: String,
+    #[serde(flatten)]
+    handle: DocumentHandle,
+}
+
+/// A document translation, it is resumed from the state file when one is left
+pub struct DocumentJob<'a> {
+    pub sl: &'a str,
+    pub tl: &'a str,
+    pub input: &'a Path,
+    pub output: &'a Path,
+    pub glossary_id: Option<&'a str>,
+    /// Delay between the status requests
+    pub interval: Duration,
+}
+
+/// `report.docx` => `report.docx.deepl-doc`, the handle is kept there until the download
+pub fn state_path(output: &Path) -> PathBuf {
+    let mut name = output.as_os_str().to_os_string();
+    name.push(".deepl-doc");
+    PathBuf::from(name)
+}
+
+/// `report.docx` => `report.DE.docx`
+pub fn output_path(input: &Path, output: &str, tl: &str) -> PathBuf {
+    match output {
+        "null" => {
+            let stem = input
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("document");
+            match input.extension().and_then(|e| e.to_str()) {
+                Some(ext) => input.with_file_name(format!("{}.{}.{}", stem, tl, ext)),
+                None => input.with_file_name(format!("{}.{}", stem, tl)),
+            }
+        }
+        _ => Path::new(output).to_path_buf(),
+    }
+}
+
+pub async fn upload_document(
+    client: &reqwest::Client,
+    base_url: &str,
+    auth_key: &str,
+    job: &DocumentJob<'_>,
+) -> Result<DocumentHandle> {
+    let file_name = job
+        .input
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("document")
+        .to_string();
+    let part = Part::bytes(fs::read(job.input)?).file_name(file_name);
+    let mut form = Form::new()
+        .text("target_lang", job.tl.to_string())
+        .part("file", part);
+    // deepl detects the source language when it is left out
+    if job.sl != "auto" {
+        form = form.text("source_lang", job.sl.to_string());
+    }
+    if let Some(id) = job.glossary_id {
+        form = form.text("glossary_id", id.to_string());
+    }
+    let res = client
+        .post(format!("{}/v2/document", base_url))
+        .header("Authorization", auth_value(auth_key))
+        .multipart(form)
+        .timeout(Duration::from_secs(TIMEOUT))
+        .send()
+        .await?;
+    Ok(check_response(res).await?.json::<DocumentHandle>().await?)
+}
+
+pub async fn document_status(
+    client: &reqwest::Client,
+    base_url: &str,
+    auth_key: &str,
+    handle: &DocumentHandle,
+) -> Result<DocumentStatus> {
+    let res = client
+        .post(format!("{}/v2/document/{}", base_url, handle.document_id))
+        .header("Authorization", auth_value(auth_key))
+        .form(&[("document_key", &handle.document_key)])
+        .timeout(Duration::from_secs(TIMEOUT))
+        .send()
+        .await?;
+    if res.status().as_u16() == 404 {
+        return Err(DeepLDocumentNotFoundError(handle.document_id.clone()).into());
+    }
+    Ok(check_response(res).await?.json::<DocumentStatus>().await?)
+}
+
+/// The result can be downloaded only once, deepl removes the document after it
+pub async fn download_document(
+    client: &reqwest::Client,
+    base_url: &str,
+    auth_key: &str,
+    handle: &DocumentHandle,
+) -> Result<Vec<u8>> {
+    let res = client
+        .post(format!(
+            "{}/v2/document/{}/result",
+            base_url, handle.document_id
+        ))
+        .header("Authorization", auth_value(auth_key))
+        .form(&[("document_key", &handle.document_key)])
+        .timeout(Duration::from_secs(TIMEOUT))
+        .send()
+        .await?;
+    Ok(check_response(res).await?.bytes().await?.to_vec())
+}
+
+/// Upload the document and save its handle next to the output
+async fn start_document(
+    client: &reqwest::Client,
+    base_url: &str,
+    auth_key: &str,
+    job: &DocumentJob<'_>,
+    input: &Path,
+    state: &Path,
+) -> Result<DocumentHandle> {
+    let handle = upload_document(client, base_url, auth_key, job).await?;
+    let saved = DocumentState {
+        input: input.to_path_buf(),
+        target_lang: job.tl.to_string(),
+        handle: handle.clone(),
+    };
+    fs::write(state, serde_json::to_string(&saved)?)?;
+    Ok(handle)
+}
+
+/// Upload, poll the status and download, an interrupted job goes on with the saved handle
+pub async fn run_document(
+    client: &reqwest::Client,
+    base_url: &str,
+    auth_key: &str,
+    job: &DocumentJob<'_>,
+) -> Result<DocumentStatus> {
+    let state = state_path(job.output);
+    let input = fs::canonicalize(job.input).unwrap_or_else(|_| job.input.to_path_buf());
+    let saved = fs::read_to_string(&state)
+        .ok()
+        .and_then(|s| serde_json::from_str::<DocumentState>(&s).ok())
+        .filter(|s| s.input == input && s.target_lang == job.tl);
+    let mut resumed = saved.is_some();
+    let mut handle = match saved {
+        Some(s) => {
+            println!("{}{}", "Resuming document ".green(), s.handle.document_id);
+            s.handle
+        }
+        None => start_document(client, base_url, auth_key, job, &input, &state).await?,
+    };
+
+    let status = loop {
+        let status = match document_status(client, base_url, auth_key, &handle).await {
+            Ok(status) => status,
+            // deepl keeps the documents only for a while, an expired handle is replaced
+            Err(e)
+                if resumed
+                    && (e.downcast_ref::<DeepLDocumentNotFoundError>().is_some()
+                        || e.downcast_ref::<DeepLAuthError>().is_some()) =>
+            {
+                let _ = fs::remove_file(&state);
+                println!("{}{}", "Uploading again, ".yellow(), e);
+                handle = start_document(client, base_url, auth_key, job, &input, &state).await?;
+                resumed = false;
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        match status.status.as_str() {
+            "done" => break status,
+            "error" => {
+                // the document is gone on the server side, a retry has to upload it again
+                let _ = fs::remove_file(&state);
+                let message = status.error_message.as_deref().unwrap_or("unknown error");
+                return Err(DeepLError(format!("document {}", message)).into());
+            }
+            s => {
+                let remaining = match status.seconds_remaining {
+                    Some(n) => format!(", about {} seconds remaining", n),
+                    None => String::new(),
+                };
+                print!("\r{}{}    ", s.yellow(), remaining);
+                std::io::stdout().flush()?;
+            }
+        }
+        tokio::time::sleep(job.interval).await;
+    };
+    println!();
+
+    let bytes = download_document(client, base_url, auth_key, &handle).await?;
+    fs::write(job.output, bytes)?;
+    let _ = fs::remove_file(&state);
+    Ok(status)
+}
+
+/// The `deepl-doc` subcommand
+pub async fn translate_document_file(
+    sl: &str,
+    tl: &str,
+    input: &str,
+    output: &str,
+    interval: u64,
+    args: &Args,
+) -> Result<()> {
+    check_api(&args.api)?;
+    check_auth_key(&args.auth_key)?;
+    let base_url = base_url(&args.auth_key, &args.endpoint);
+    let client = build_client(&args.proxy);
+    let glossary_id =
+        resolve_glossary(&client, base_url, &args.auth_key, sl, tl, &args.glossary).await?;
+    let input = Path::new(input);
+    let output = output_path(input, output, tl);
+    let job = DocumentJob {
+        sl,
+        tl,
+        input,
+        output: &output,
+        glossary_id: glossary_id.as_deref(),
+        interval: Duration::from_secs(interval),
+    };
+    let status = run_document(&client, base_url, &args.auth_key, &job).await?;
+    if let Some(n) = status.billed_characters {
+        println!(
+            "{}{}",
+            "Billed ".green(),
+            format!("{} characters", n).green().bold()
+        );
+    }
+    println!("{}{}", "Saved to ".green(), output.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::HttpResponse;
+    use crate::test_support::mock_server;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Counts the uploads, the document is done after the second status request and `fail` is not accepted
+    async fn document_server(uploads: Arc<AtomicUsize>) -> String {
+        let polls = AtomicUsize::new(0);
+        mock_server(move |request| {
+            let key_ok = request
+                .form()
+                .iter()
+                .any(|(k, v)| k == "document_key" && v == "KEY");
+            match (request.method.as_str(), request.path()) {
+                ("POST", "/v2/document") => {
+                    let content_type = request.header("Content-Type").unwrap_or("");
+                    let body = String::from_utf8_lossy(&request.body);
+                    assert!(content_type.starts_with("multipart/form-data"));
+                    assert!(body.contains("filename=\"report.txt\""));
+                    assert!(!body.contains("name=\"source_lang\""));
+                    if body.contains("fail") {
+                        return HttpResponse::json(403, json!({ "message": "Forbidden" }));
+                    }
+                    uploads.fetch_add(1, Ordering::SeqCst);
+                    HttpResponse::json(200, json!({ "document_id": "DOC", "document_key": "KEY" }))
+                }
+                ("POST", "/v2/document/DOC") if key_ok => {
+                    match polls.fetch_add(1, Ordering::SeqCst) {
+                        0 => HttpResponse::json(
+                            200,
+                            json!({ "document_id": "DOC", "status": "translating", "seconds_remaining": 1 }),
+                        ),
+                        _ => HttpResponse::json(
+                            200,
+                            json!({ "document_id": "DOC", "status": "done", "billed_characters": 50000 }),
+                        ),
+                    }
+                }
+                ("POST", "/v2/document/DOC/result") if key_ok => HttpResponse {
+                    status: 200,
+                    content_type: "application/octet-stream",
+                    body: b"Bericht".to_vec(),
+                },
+                _ => HttpResponse::json(404, json!({ "message": "Document not found" })),
+            }
+        })
+        .await
+    }
+
+    #[test]
+    fn test_paths() {
+        let input = Path::new("docs/report.docx");
+        assert_eq!(
+            output_path(input, "null", "DE"),
+            Path::new("docs/report.DE.docx")
+        );
+        assert_eq!(output_path(input, "out.docx", "DE"), Path::new("out.docx"));
+        assert_eq!(
+            state_path(Path::new("out.docx")),
+            Path::new("out.docx.deepl-doc")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_document() {
+        let uploads = Arc::new(AtomicUsize::new(0));
+        let url = document_server(uploads.clone()).await;
+        let client = reqwest::Client::new();
+        let dir = std::env::temp_dir().join(format!("deepl-doc-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("report.txt");
+        let output = dir.join("report.DE.txt");
+        fs::write(&input, "Report").unwrap();
+        let job = DocumentJob {
+            sl: "auto",
+            tl: "DE",
+            input: &input,
+            output: &output,
+            glossary_id: None,
+            interval: Duration::from_millis(10),
+        };
+
+        let status = run_document(&client, &url, "key", &job).await.unwrap();
+        assert_eq!(status.billed_characters, Some(50000));
+        assert_eq!(fs::read_to_string(&output).unwrap(), "Bericht");
+        assert!(!state_path(&output).exists());
+        assert_eq!(uploads.load(Ordering::SeqCst), 1);
+
+        // an interrupted job is resumed with the saved handle and not uploaded again
+        let state = |tl: &str, key: &str| {
+            let state = json!({
+                "input": fs::canonicalize(&input).unwrap(),
+                "target_lang": tl,
+                "document_id": "DOC",
+                "document_key": key,
+            });
+            fs::write(state_path(&output), state.to_string()).unwrap();
+        };
+        fs::remove_file(&output).unwrap();
+        state("DE", "KEY");
+        run_document(&client, &url, "key", &job).await.unwrap();
+        assert_eq!(fs::read_to_string(&output).unwrap(), "Bericht");
+        assert_eq!(uploads.load(Ordering::SeqCst), 1);
+
+        // the state of another target language is not resumed
+        state("FR", "KEY");
+        run_document(&client, &url, "key", &job).await.unwrap();
+        assert_eq!(uploads.load(Ordering::SeqCst), 2);
+
+        // a handle deepl no longer knows is dropped and the document is uploaded again
+        state("DE", "OLD");
+        run_document(&client, &url, "key", &job).await.unwrap();
+        assert_eq!(fs::read_to_string(&output).unwrap(), "Bericht");
+        assert!(!state_path(&output).exists());
+        assert_eq!(uploads.load(Ordering::SeqCst), 3);
+
+        // a failed upload leaves no state behind
+        fs::write(&input, "fail").unwrap();
+        state("DE", "OLD");
+        let e = run_document(&client, &url, "key", &job).await.unwrap_err();
+        assert!(e.downcast_ref::<DeepLAuthError>().is_some());
+        assert!(!state_path(&output).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,322 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::deepl_api::{auth_value, base_url, check_api, check_auth_key, check_response};
+use crate::errors::DeepLError;
+use crate::errors::DeepLOptionError;
+use crate::utils::build_client;
+use crate::Args;
+use crate::GlossaryAction;
+use crate::TIMEOUT;
+
+/// The glossary ids found by name, `(base_url|name|sl|tl, id)`
+static GLOSSARY_IDS: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Glossary {
+    pub glossary_id: String,
+    pub name: String,
+    pub ready: bool,
+    pub source_lang: String,
+    pub target_lang: String,
+    pub creation_time: String,
+    pub entry_count: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Glossaries {
+    pub glossaries: Vec<Glossary>,
+}
+
+/// The form of `POST /v2/glossaries`
+#[derive(Serialize, Debug)]
+pub struct NewGlossary<'a> {
+    pub name: &'a str,
+    pub source_lang: &'a str,
+    pub target_lang: &'a str,
+    pub entries: &'a str,
+    /// tsv or csv
+    pub entries_format: &'a str,
+}
+
+/// `EN-US` => `en`, a glossary is made for the base languages
+pub fn glossary_lang(lang: &str) -> String {
+    lang.split('-').next().unwrap_or(lang).to_lowercase()
+}
+
+/// The glossary ids are uuids, everything else is a name
+fn is_glossary_id(s: &str) -> bool {
+    s.len() == 36
+        && s.chars().filter(|c| *c == '-').count() == 4
+        && s.chars().all(|c| c == '-' || c.is_ascii_hexdigit())
+}
+
+pub async fn create_glossary(
+    client: &reqwest::Client,
+    base_url: &str,
+    auth_key: &str,
+    glossary: &NewGlossary<'_>,
+) -> Result<Glossary> {
+    let res = client
+        .post(format!("{}/v2/glossaries", base_url))
+        .header("Authorization", auth_value(auth_key))
+        .form(glossary)
+        .timeout(Duration::from_secs(TIMEOUT))
+        .send()
+        .await?;
+    Ok(check_response(res).await?.json::<Glossary>().await?)
+}
+
+pub async fn list_glossaries(
+    client: &reqwest::Client,
+    base_url: &str,
+    auth_key: &str,
+) -> Result<Vec<Glossary>> {
+    let res = client
+        .get(format!("{}/v2/glossaries", base_url))
+        .header("Authorization", auth_value(auth_key))
+        .timeout(Duration::from_secs(TIMEOUT))
+        .send()
+        .await?;
+    Ok(check_response(res)
+        .await?
+        .json::<Glossaries>()
+        .await?
+        .glossaries)
+}
+
+pub async fn get_glossary(
+    client: &reqwest::Client,
+    base_url: &str,
+    auth_key: &str,
+    id: &str,
+) -> Result<Glossary> {
+    let res = client
+        .get(format!("{}/v2/glossaries/{}", base_url, id))
+        .header("Authorization", auth_value(auth_key))
+        .timeout(Duration::from_secs(TIMEOUT))
+        .send()
+        .await?;
+    Ok(check_response(res).await?.json::<Glossary>().await?)
+}
+
+/// The entries in tsv, one `source<TAB>target` per line
+pub async fn glossary_entries(
+    client: &reqwest::Client,
+    base_url: &str,
+    auth_key: &str,
+    id: &str,
+) -> Result<String> {
+    let res = client
+        .get(format!("{}/v2/glossaries/{}/entries", base_url, id))
+        .header("Authorization", auth_value(auth_key))
+        .header("Accept", "text/tab-separated-values")
+        .timeout(Duration::from_secs(TIMEOUT))
+        .send()
+        .await?;
+    Ok(check_response(res).await?.text().await?)
+}
+
+pub async fn delete_glossary(
+    client: &reqwest::Client,
+    base_url: &str,
+    auth_key: &str,
+    id: &str,
+) -> Result<()> {
+    let res = client
+        .delete(format!("{}/v2/glossaries/{}", base_url, id))
+        .header("Authorization", auth_value(auth_key))
+        .timeout(Duration::from_secs(TIMEOUT))
+        .send()
+        .await?;
+    check_response(res).await?;
+    GLOSSARY_IDS
+        .lock()
+        .unwrap()
+        .retain(|(_, glossary_id)| glossary_id != id);
+    Ok(())
+}
+
+/// The glossary id for the language pair, a name is looked up once and then cached
+pub async fn resolve_glossary(
+    client: &reqwest::Client,
+    base_url: &str,
+    auth_key: &str,
+    sl: &str,
+    tl: &str,
+    glossary: &str,
+) -> Result<Option<String>> {
+    if glossary == "null" || glossary.is_empty() {
+        return Ok(None);
+    }
+    // a glossary is made for a language pair, deepl does not detect the source with one
+    if sl == "auto" {
+        return Err(DeepLOptionError(
+            "deepl requires the source language with a glossary, set it with -s".to_string(),
+        )
+        .into());
+    }
+    if is_glossary_id(glossary) {
+        return Ok(Some(glossary.to_string()));
+    }
+    let (sl, tl) = (glossary_lang(sl), glossary_lang(tl));
+    let key = format!("{}|{}|{}|{}", base_url, glossary, sl, tl);
+    if let Some((_, id)) = GLOSSARY_IDS.lock().unwrap().iter().find(|(k, _)| *k == key) {
+        return Ok(Some(id.clone()));
+    }
+    let found = list_glossaries(client, base_url, auth_key)
+        .await?
+        .into_iter()
+        .find(|g| g.name == glossary && g.source_lang == sl && g.target_lang == tl);
+    match found {
+        Some(g) => {
+            GLOSSARY_IDS
+                .lock()
+                .unwrap()
+                .push((key, g.glossary_id.clone()));
+            Ok(Some(g.glossary_id))
+        }
+        None => Err(DeepLError(format!(
+            "no glossary named {} for {} -> {}",
+            glossary, sl, tl
+        ))
+        .into()),
+    }
+}
+
+fn print_glossary(g: &Glossary) {
+    println!(
+        "{}  {}  {} -> {}  {} entries{}",
+        g.glossary_id,
+        g.name,
+        g.source_lang,
+        g.target_lang,
+        g.entry_count,
+        match g.ready {
+            true => "",
+            false => "  (not ready)",
+        }
+    );
+}
+
+/// The `glossary` subcommand, the languages come from `-s` and `-t`
+pub async fn glossary_command(
+    action: &GlossaryAction,
+    sl: &str,
+    tl: &str,
+    args: &Args,
+) -> Result<()> {
+    check_api(&args.api)?;
+    check_auth_key(&args.auth_key)?;
+    let base_url = base_url(&args.auth_key, &args.endpoint);
+    let auth_key = args.auth_key.as_str();
+    let client = build_client(&args.proxy);
+    match action {
+        GlossaryAction::Create { name, file } => {
+            let entries = std::fs::read_to_string(file)?;
+            let entries_format = match Path::new(file).extension().and_then(|e| e.to_str()) {
+                Some(e) if e.eq_ignore_ascii_case("csv") => "csv",
+                _ => "tsv",
+            };
+            let (sl, tl) = (glossary_lang(sl), glossary_lang(tl));
+            let new_glossary = NewGlossary {
+                name,
+                source_lang: &sl,
+                target_lang: &tl,
+                entries: &entries,
+                entries_format,
+            };
+            let g = create_glossary(&client, base_url, auth_key, &new_glossary).await?;
+            print_glossary(&g);
+        }
+        GlossaryAction::List => {
+            for g in list_glossaries(&client, base_url, auth_key).await? {
+                print_glossary(&g);
+            }
+        }
+        GlossaryAction::Show { id, entries } => {
+            let g = get_glossary(&client, base_url, auth_key, id).await?;
+            println!("{}", serde_json::to_string_pretty(&g)?);
+            if *entries {
+                print!(
+                    "{}",
+                    glossary_entries(&client, base_url, auth_key, id).await?
+                );
+            }
+        }
+        GlossaryAction::Delete { id } => {
+            delete_glossary(&client, base_url, auth_key, id).await?;
+            println!("glossary {} deleted", id);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deepl_api::{translate_deepl, DeepLOptions};
+    use crate::test_support::{deepl_server, GLOSSARY_ID as ID};
+
+    #[test]
+    fn test_glossary_id() {
+        assert!(is_glossary_id(ID));
+        assert!(!is_glossary_id("papers"));
+        assert_eq!(glossary_lang("EN-US"), "en");
+        assert_eq!(glossary_lang("DE"), "de");
+    }
+
+    #[tokio::test]
+    async fn test_glossary() {
+        let url = deepl_server().await;
+        let client = reqwest::Client::new();
+        let new_glossary = NewGlossary {
+            name: "papers",
+            source_lang: "en",
+            target_lang: "de",
+            entries: "model\tModell\nlayer\tSchicht\n",
+            entries_format: "tsv",
+        };
+        let g = create_glossary(&client, &url, "key", &new_glossary)
+            .await
+            .unwrap();
+        assert_eq!(g.entry_count, 2);
+        assert_eq!(
+            list_glossaries(&client, &url, "key").await.unwrap().len(),
+            1
+        );
+        assert_eq!(
+            glossary_entries(&client, &url, "key", ID).await.unwrap(),
+            "model\tModell\n"
+        );
+
+        // the name is resolved for the language pair
+        let mut options = DeepLOptions::with_endpoint(&url);
+        options.glossary = "papers";
+        let r = translate_deepl("EN", "DE", "model", "null", "key", &options)
+            .await
+            .unwrap();
+        assert_eq!(r[0].trans, format!("{}:model", ID));
+        let e = translate_deepl("EN", "JA", "model", "null", "key", &options)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "deepl error: no glossary named papers for en -> ja"
+        );
+        let e = translate_deepl("auto", "DE", "model", "null", "key", &options)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "invalid deepl option: deepl requires the source language with a glossary, set it with -s"
+        );
+
+        delete_glossary(&client, &url, "key", ID).await.unwrap();
+        let e = get_glossary(&client, &url, "key", ID).await.unwrap_err();
+        assert_eq!(e.to_string(), "deepl error: Glossary not found (404)");
+    }
+}
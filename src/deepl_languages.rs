@@ -0,0 +1,356 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::deepl_api::{auth_value, base_url, check_response};
+use crate::errors::DeepLLanguageError;
+use crate::utils::build_client;
+use crate::Args;
+use crate::TIMEOUT;
+
+/// The cached lists are fetched again after a week
+const CACHE_TTL: u64 = 7 * 24 * 3600;
+
+/// Used when the lists can not be fetched, `(code, name, supports_formality)`
+const BUNDLED_SOURCE: &[(&str, &str, bool)] = &[
+    ("AR", "Arabic", false),
+    ("BG", "Bulgarian", false),
+    ("CS", "Czech", false),
+    ("DA", "Danish", false),
+    ("DE", "German", false),
+    ("EL", "Greek", false),
+    ("EN", "English", false),
+    ("ES", "Spanish", false),
+    ("ET", "Estonian", false),
+    ("FI", "Finnish", false),
+    ("FR", "French", false),
+    ("HU", "Hungarian", false),
+    ("ID", "Indonesian", false),
+    ("IT", "Italian", false),
+    ("JA", "Japanese", false),
+    ("KO", "Korean", false),
+    ("LT", "Lithuanian", false),
+    ("LV", "Latvian", false),
+    ("NB", "Norwegian", false),
+    ("NL", "Dutch", false),
+    ("PL", "Polish", false),
+    ("PT", "Portuguese", false),
+    ("RO", "Romanian", false),
+    ("RU", "Russian", false),
+    ("SK", "Slovak", false),
+    ("SL", "Slovenian", false),
+    ("SV", "Swedish", false),
+    ("TR", "Turkish", false),
+    ("UK", "Ukrainian", false),
+    ("ZH", "Chinese", false),
+];
+
+const BUNDLED_TARGET: &[(&str, &str, bool)] = &[
+    ("AR", "Arabic", false),
+    ("BG", "Bulgarian", false),
+    ("CS", "Czech", false),
+    ("DA", "Danish", false),
+    ("DE", "German", true),
+    ("EL", "Greek", false),
+    ("EN-GB", "English (British)", false),
+    ("EN-US", "English (American)", false),
+    ("ES", "Spanish", true),
+    ("ET", "Estonian", false),
+    ("FI", "Finnish", false),
+    ("FR", "French", true),
+    ("HU", "Hungarian", false),
+    ("ID", "Indonesian", false),
+    ("IT", "Italian", true),
+    ("JA", "Japanese", true),
+    ("KO", "Korean", false),
+    ("LT", "Lithuanian", false),
+    ("LV", "Latvian", false),
+    ("NB", "Norwegian", false),
+    ("NL", "Dutch", true),
+    ("PL", "Polish", true),
+    ("PT-BR", "Portuguese (Brazilian)", true),
+    ("PT-PT", "Portuguese (European)", true),
+    ("RO", "Romanian", false),
+    ("RU", "Russian", true),
+    ("SK", "Slovak", false),
+    ("SL", "Slovenian", false),
+    ("SV", "Swedish", false),
+    ("TR", "Turkish", false),
+    ("UK", "Ukrainian", false),
+    ("ZH", "Chinese (simplified)", false),
+    ("ZH-HANS", "Chinese (simplified)", false),
+    ("ZH-HANT", "Chinese (traditional)", false),
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeepLLanguage {
+    pub language: String,
+    pub name: String,
+    #[serde(default)]
+    pub supports_formality: bool,
+}
+
+/// The source and the target lists are not the same, `EN` is only a source
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeepLLanguages {
+    pub source: Vec<DeepLLanguage>,
+    pub target: Vec<DeepLLanguage>,
+    /// Unix seconds
+    pub fetched_at: u64,
+    /// The lists of another endpoint, or of the free api for a pro key, are not used
+    #[serde(default)]
+    pub base_url: String,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+pub fn bundled() -> DeepLLanguages {
+    let convert = |table: &[(&str, &str, bool)]| {
+        table
+            .iter()
+            .map(|(language, name, supports_formality)| DeepLLanguage {
+                language: language.to_string(),
+                name: name.to_string(),
+                supports_formality: *supports_formality,
+            })
+            .collect()
+    };
+    DeepLLanguages {
+        source: convert(BUNDLED_SOURCE),
+        target: convert(BUNDLED_TARGET),
+        fetched_at: 0,
+        base_url: String::new(),
+    }
+}
+
+/// `~/.cache/translator-rs/deepl-languages.json`, `%LOCALAPPDATA%` on windows
+pub fn cache_path() -> Option<PathBuf> {
+    let dir = match std::env::var("XDG_CACHE_HOME").or(std::env::var("LOCALAPPDATA")) {
+        Ok(d) => PathBuf::from(d),
+        Err(_) => PathBuf::from(std::env::var("HOME").ok()?).join(".cache"),
+    };
+    Some(dir.join("translator-rs").join("deepl-languages.json"))
+}
+
+async fn fetch_list(
+    client: &reqwest::Client,
+    base_url: &str,
+    auth_key: &str,
+    list_type: &str,
+) -> Result<Vec<DeepLLanguage>> {
+    let res = client
+        .get(format!("{}/v2/languages", base_url))
+        .query(&[("type", list_type)])
+        .header("Authorization", auth_value(auth_key))
+        .timeout(Duration::from_secs(TIMEOUT))
+        .send()
+        .await?;
+    Ok(check_response(res)
+        .await?
+        .json::<Vec<DeepLLanguage>>()
+        .await?)
+}
+
+pub async fn fetch_languages(
+    client: &reqwest::Client,
+    base_url: &str,
+    auth_key: &str,
+) -> Result<DeepLLanguages> {
+    Ok(DeepLLanguages {
+        source: fetch_list(client, base_url, auth_key, "source").await?,
+        target: fetch_list(client, base_url, auth_key, "target").await?,
+        fetched_at: now(),
+        base_url: base_url.to_string(),
+    })
+}
+
+/// The cache while it is fresh, then the api, then an old cache and at last the bundled lists
+pub async fn load_languages(
+    client: &reqwest::Client,
+    base_url: &str,
+    auth_key: &str,
+    cache: Option<&Path>,
+) -> DeepLLanguages {
+    let cached = cache
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|s| serde_json::from_str::<DeepLLanguages>(&s).ok())
+        .filter(|c| c.base_url == base_url);
+    if let Some(c) = cached.as_ref().filter(|c| now() < c.fetched_at + CACHE_TTL) {
+        return c.clone();
+    }
+    if auth_key != "null" && !auth_key.is_empty() {
+        if let Ok(langs) = fetch_languages(client, base_url, auth_key).await {
+            // a cache which can not be written is fetched again next time
+            if let (Some(p), Ok(json)) = (cache, serde_json::to_string(&langs)) {
+                let _ = p.parent().map(std::fs::create_dir_all);
+                let _ = std::fs::write(p, json);
+            }
+            return langs;
+        }
+    }
+    cached.unwrap_or_else(bundled)
+}
+
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cur = row[j + 1];
+            row[j + 1] = match ca == *cb {
+                true => prev,
+                false => prev.min(cur).min(row[j]) + 1,
+            };
+            prev = cur;
+        }
+    }
+    row[b.len()]
+}
+
+/// `EN` => `EN-GB, EN-US`, `Germn` => `DE`, the closer matches hide the others
+fn suggest(lang: &str, list: &[DeepLLanguage]) -> Vec<String> {
+    let lower = lang.to_lowercase();
+    let variant = |l: &DeepLLanguage| {
+        l.language
+            .to_lowercase()
+            .starts_with(&format!("{}-", lower))
+    };
+    let part = |l: &DeepLLanguage| lower.len() >= 3 && l.name.to_lowercase().contains(&lower);
+    let typo = |l: &DeepLLanguage| {
+        distance(&lower, &l.language.to_lowercase()) <= 1
+            || distance(&lower, &l.name.to_lowercase()) <= 2
+    };
+    let rules: [&dyn Fn(&DeepLLanguage) -> bool; 3] = [&variant, &part, &typo];
+    for rule in rules {
+        let found: Vec<String> = list
+            .iter()
+            .filter(|l| rule(l))
+            .take(5)
+            .map(|l| format!("{} ({})", l.language, l.name))
+            .collect();
+        if !found.is_empty() {
+            return found;
+        }
+    }
+    Vec::new()
+}
+
+/// The code of a language name or code, `auto` is a source only
+pub fn resolve_lang(lang: &str, list: &[DeepLLanguage], target: bool) -> Result<String> {
+    if !target && lang.eq_ignore_ascii_case("auto") {
+        return Ok("auto".to_string());
+    }
+    let find = |x: &str| {
+        list.iter()
+            .find(|l| l.language.eq_ignore_ascii_case(x) || l.name.eq_ignore_ascii_case(x))
+            .map(|l| l.language.to_uppercase())
+    };
+    if let Some(code) = find(lang) {
+        return Ok(code);
+    }
+    // the sources have no variants, `EN-US` is read as `EN`
+    if let Some((base, _)) = lang.split_once('-').filter(|_| !target) {
+        if let Some(code) = find(base) {
+            return Ok(code);
+        }
+    }
+    Err(DeepLLanguageError {
+        lang: lang.to_string(),
+        target,
+        suggestions: suggest(lang, list),
+    }
+    .into())
+}
+
+/// Check `-s` and `-t` against the deepl lists before anything is translated
+pub async fn standardized_deepl_lang(sl: &str, tl: &str, args: &Args) -> Result<(String, String)> {
+    let client = build_client(&args.proxy);
+    let base_url = base_url(&args.auth_key, &args.endpoint);
+    let cache = cache_path();
+    let langs = load_languages(&client, base_url, &args.auth_key, cache.as_deref()).await;
+    Ok((
+        resolve_lang(sl, &langs.source, false)?,
+        resolve_lang(tl, &langs.target, true)?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::HttpResponse;
+    use crate::test_support::mock_server;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_resolve_lang() {
+        let langs = bundled();
+        assert_eq!(resolve_lang("English", &langs.source, false).unwrap(), "EN");
+        assert_eq!(resolve_lang("en-us", &langs.source, false).unwrap(), "EN");
+        assert_eq!(resolve_lang("Auto", &langs.source, false).unwrap(), "auto");
+        assert_eq!(
+            resolve_lang("Chinese (Simplified)", &langs.target, true).unwrap(),
+            "ZH"
+        );
+        assert_eq!(resolve_lang("pt-br", &langs.target, true).unwrap(), "PT-BR");
+
+        let e = resolve_lang("EN", &langs.target, true).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "deepl has no target language EN, did you mean EN-GB (English (British)), EN-US (English (American))?"
+        );
+        let e = resolve_lang("Germn", &langs.target, true).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "deepl has no target language Germn, did you mean DE (German)?"
+        );
+        assert!(resolve_lang("auto", &langs.target, true).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_load_languages() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let url = mock_server(move |request| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            match request.param("type") {
+                Some("source") => HttpResponse::json(200, json!([{ "language": "EN", "name": "English" }])),
+                _ => HttpResponse::json(
+                    200,
+                    json!([{ "language": "EN-US", "name": "English (American)", "supports_formality": false }]),
+                ),
+            }
+        })
+        .await;
+        let client = reqwest::Client::new();
+        let dir = std::env::temp_dir().join(format!("deepl-languages-{}", std::process::id()));
+        let cache = dir.join("deepl-languages.json");
+
+        let langs = load_languages(&client, &url, "key", Some(&cache)).await;
+        assert_eq!(langs.target[0].language, "EN-US");
+        assert!(cache.exists());
+
+        // the fresh cache is used without the api
+        let down = "http://127.0.0.1:1";
+        let langs = load_languages(&client, &url, "key", Some(&cache)).await;
+        assert_eq!(langs.source.len(), 1);
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+        // but not for another endpoint
+        let langs = load_languages(&client, down, "key", Some(&cache)).await;
+        assert_eq!(langs.source.len(), BUNDLED_SOURCE.len());
+
+        // no cache and no api
+        std::fs::remove_dir_all(&dir).unwrap();
+        let langs = load_languages(&client, down, "key", Some(&cache)).await;
+        assert_eq!(langs.source.len(), BUNDLED_SOURCE.len());
+    }
+}
@@ -4,6 +4,7 @@ use std::fmt;
 /// UnsupportApiError
 #[derive(Debug, Clone)]
 pub struct UnsupportApiError;
+#[allow(clippy::write_literal)]
 impl fmt::Display for UnsupportApiError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", "unsupported api")
@@ -14,6 +15,7 @@ impl Error for UnsupportApiError {}
 /// UnsupportOsError
 #[derive(Debug, Clone)]
 pub struct UnsupportOsError;
+#[allow(clippy::write_literal)]
 impl fmt::Display for UnsupportOsError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", "unsupported os")
@@ -21,12 +23,469 @@ impl fmt::Display for UnsupportOsError {
 }
 impl Error for UnsupportOsError {}
 
-/// UnsupportOsError
+/// DeepLEmptyAuthKeyError
 #[derive(Debug, Clone)]
 pub struct DeepLEmptyAuthKeyError;
+#[allow(clippy::write_literal)]
 impl fmt::Display for DeepLEmptyAuthKeyError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", "please privode a deepl auth key")
     }
 }
-impl Error for DeepLEmptyAuthKeyError {}
\ No newline at end of file
+impl Error for DeepLEmptyAuthKeyError {}
+
+/// UnsupportSubtitleError
+#[derive(Debug, Clone)]
+pub struct UnsupportSubtitleError;
+impl fmt::Display for UnsupportSubtitleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "unsupported subtitle file, only srt and vtt are supported"
+        )
+    }
+}
+impl Error for UnsupportSubtitleError {}
+
+/// UnsupportResourceError
+#[derive(Debug, Clone)]
+pub struct UnsupportResourceError;
+impl fmt::Display for UnsupportResourceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "unsupported resource file, only json, yaml and toml are supported"
+        )
+    }
+}
+impl Error for UnsupportResourceError {}
+
+/// BadRequestError
+#[derive(Debug, Clone)]
+pub struct BadRequestError;
+impl fmt::Display for BadRequestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "malformed http request")
+    }
+}
+impl Error for BadRequestError {}
+
+/// RequestTooLargeError
+#[derive(Debug, Clone)]
+pub struct RequestTooLargeError;
+impl fmt::Display for RequestTooLargeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "http request is too large")
+    }
+}
+impl Error for RequestTooLargeError {}
+
+/// RequestTimeoutError
+#[derive(Debug, Clone)]
+pub struct RequestTimeoutError;
+impl fmt::Display for RequestTimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "http request did not arrive in time")
+    }
+}
+impl Error for RequestTimeoutError {}
+
+/// ServeTokenError
+#[derive(Debug, Clone)]
+pub struct ServeTokenError(pub String);
+impl fmt::Display for ServeTokenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} is not a loopback address, please provide a --token",
+            self.0
+        )
+    }
+}
+impl Error for ServeTokenError {}
+
+/// NativeMessageTooLargeError
+#[derive(Debug, Clone)]
+pub struct NativeMessageTooLargeError;
+impl fmt::Display for NativeMessageTooLargeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "native message is larger than 1 MiB")
+    }
+}
+impl Error for NativeMessageTooLargeError {}
+
+/// UnsupportBrowserError
+#[derive(Debug, Clone)]
+pub struct UnsupportBrowserError;
+impl fmt::Display for UnsupportBrowserError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "unsupported browser, only chrome, chromium, edge and firefox are supported"
+        )
+    }
+}
+impl Error for UnsupportBrowserError {}
+
+/// LibreTranslateError
+#[derive(Debug, Clone)]
+pub struct LibreTranslateError(pub String);
+impl fmt::Display for LibreTranslateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "libretranslate error: {}", self.0)
+    }
+}
+impl Error for LibreTranslateError {}
+
+/// LlmError
+#[derive(Debug, Clone)]
+pub struct LlmError(pub String);
+impl fmt::Display for LlmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "llm error: {}", self.0)
+    }
+}
+impl Error for LlmError {}
+
+/// AzureEmptyAuthKeyError
+#[derive(Debug, Clone)]
+pub struct AzureEmptyAuthKeyError;
+impl fmt::Display for AzureEmptyAuthKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "please provide an azure subscription key")
+    }
+}
+impl Error for AzureEmptyAuthKeyError {}
+
+/// AzureError
+#[derive(Debug, Clone)]
+pub struct AzureError(pub String);
+impl fmt::Display for AzureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "azure error: {}", self.0)
+    }
+}
+impl Error for AzureError {}
+
+/// GoogleCloudEmptyAuthKeyError
+#[derive(Debug, Clone)]
+pub struct GoogleCloudEmptyAuthKeyError;
+impl fmt::Display for GoogleCloudEmptyAuthKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "please provide a google cloud api key")
+    }
+}
+impl Error for GoogleCloudEmptyAuthKeyError {}
+
+/// GoogleCloudError
+#[derive(Debug, Clone)]
+pub struct GoogleCloudError(pub String);
+impl fmt::Display for GoogleCloudError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "google cloud error: {}", self.0)
+    }
+}
+impl Error for GoogleCloudError {}
+
+/// BaiduEmptyAuthKeyError
+#[derive(Debug, Clone)]
+pub struct BaiduEmptyAuthKeyError;
+impl fmt::Display for BaiduEmptyAuthKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "please provide the baidu appid (--app-id) and secret key (--auth-key)"
+        )
+    }
+}
+impl Error for BaiduEmptyAuthKeyError {}
+
+/// BaiduAuthError
+#[derive(Debug, Clone)]
+pub struct BaiduAuthError;
+impl fmt::Display for BaiduAuthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "baidu error: the appid or the secret key is invalid")
+    }
+}
+impl Error for BaiduAuthError {}
+
+/// BaiduRateLimitError
+#[derive(Debug, Clone)]
+pub struct BaiduRateLimitError;
+impl fmt::Display for BaiduRateLimitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "baidu error: too many requests, please slow down")
+    }
+}
+impl Error for BaiduRateLimitError {}
+
+/// BaiduBalanceError
+#[derive(Debug, Clone)]
+pub struct BaiduBalanceError;
+impl fmt::Display for BaiduBalanceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "baidu error: the account balance is insufficient")
+    }
+}
+impl Error for BaiduBalanceError {}
+
+/// BaiduError
+#[derive(Debug, Clone)]
+pub struct BaiduError(pub String);
+impl fmt::Display for BaiduError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "baidu error: {}", self.0)
+    }
+}
+impl Error for BaiduError {}
+
+/// YoudaoEmptyAuthKeyError
+#[derive(Debug, Clone)]
+pub struct YoudaoEmptyAuthKeyError;
+impl fmt::Display for YoudaoEmptyAuthKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "please provide the youdao app key (--app-id) and app secret (--auth-key)"
+        )
+    }
+}
+impl Error for YoudaoEmptyAuthKeyError {}
+
+/// YoudaoError
+#[derive(Debug, Clone)]
+pub struct YoudaoError(pub String);
+impl fmt::Display for YoudaoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "youdao error: {}", self.0)
+    }
+}
+impl Error for YoudaoError {}
+
+/// AwsCredentialsError
+#[derive(Debug, Clone)]
+pub struct AwsCredentialsError;
+impl fmt::Display for AwsCredentialsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "aws credentials not found, set AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY or use the ~/.aws/credentials file"
+        )
+    }
+}
+impl Error for AwsCredentialsError {}
+
+/// AwsError
+#[derive(Debug, Clone)]
+pub struct AwsError(pub String);
+impl fmt::Display for AwsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "aws error: {}", self.0)
+    }
+}
+impl Error for AwsError {}
+
+/// ExecEmptyCommandError
+#[derive(Debug, Clone)]
+pub struct ExecEmptyCommandError;
+impl fmt::Display for ExecEmptyCommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "please provide the command of the exec provider (--exec-command)"
+        )
+    }
+}
+impl Error for ExecEmptyCommandError {}
+
+/// ExecError
+#[derive(Debug, Clone)]
+pub struct ExecError(pub String);
+impl fmt::Display for ExecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "exec error: {}", self.0)
+    }
+}
+impl Error for ExecError {}
+
+/// OfflineInjectedError
+#[derive(Debug, Clone)]
+pub struct OfflineInjectedError;
+impl fmt::Display for OfflineInjectedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "injected failure of the offline provider")
+    }
+}
+impl Error for OfflineInjectedError {}
+
+/// DeepLError
+#[derive(Debug, Clone)]
+pub struct DeepLError(pub String);
+impl fmt::Display for DeepLError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "deepl error: {}", self.0)
+    }
+}
+impl Error for DeepLError {}
+
+/// DeepLQuotaExceededError
+#[derive(Debug, Clone)]
+pub struct DeepLQuotaExceededError;
+impl fmt::Display for DeepLQuotaExceededError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "deepl quota exceeded, the character limit of this billing period is reached"
+        )
+    }
+}
+impl Error for DeepLQuotaExceededError {}
+
+/// DeepLBudgetError
+#[derive(Debug, Clone)]
+pub struct DeepLBudgetError {
+    pub chars: u64,
+    pub remaining: u64,
+}
+impl fmt::Display for DeepLBudgetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "deepl budget: {} characters to send but only {} left, nothing was sent",
+            self.chars, self.remaining
+        )
+    }
+}
+impl Error for DeepLBudgetError {}
+
+/// DeepLOptionError
+#[derive(Debug, Clone)]
+pub struct DeepLOptionError(pub String);
+impl fmt::Display for DeepLOptionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid deepl option: {}", self.0)
+    }
+}
+impl Error for DeepLOptionError {}
+
+/// DeepLAuthError
+#[derive(Debug, Clone)]
+pub struct DeepLAuthError;
+impl fmt::Display for DeepLAuthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "deepl rejected the auth key (403), check --auth-key, the free keys end with :fx"
+        )
+    }
+}
+impl Error for DeepLAuthError {}
+
+/// DeepLTooLargeError
+#[derive(Debug, Clone)]
+pub struct DeepLTooLargeError;
+impl fmt::Display for DeepLTooLargeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "the request is too large for deepl (413), select a shorter text"
+        )
+    }
+}
+impl Error for DeepLTooLargeError {}
+
+/// DeepLRateLimitError
+#[derive(Debug, Clone)]
+pub struct DeepLRateLimitError;
+impl fmt::Display for DeepLRateLimitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "too many requests to deepl (429), wait a moment and try again"
+        )
+    }
+}
+impl Error for DeepLRateLimitError {}
+
+/// DeepLServerError
+#[derive(Debug, Clone)]
+pub struct DeepLServerError(pub u16);
+impl fmt::Display for DeepLServerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "deepl is unavailable ({}), try again later", self.0)
+    }
+}
+impl Error for DeepLServerError {}
+
+/// DeepLLanguageError
+#[derive(Debug, Clone)]
+pub struct DeepLLanguageError {
+    pub lang: String,
+    pub target: bool,
+    pub suggestions: Vec<String>,
+}
+impl fmt::Display for DeepLLanguageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self.target {
+            true => "target",
+            false => "source",
+        };
+        write!(f, "deepl has no {} language {}", kind, self.lang)?;
+        match self.suggestions.is_empty() {
+            true => Ok(()),
+            false => write!(f, ", did you mean {}?", self.suggestions.join(", ")),
+        }
+    }
+}
+impl Error for DeepLLanguageError {}
+
+/// DeepLDocumentNotFoundError
+#[derive(Debug, Clone)]
+pub struct DeepLDocumentNotFoundError(pub String);
+impl fmt::Display for DeepLDocumentNotFoundError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "deepl has no document {} (404)", self.0)
+    }
+}
+impl Error for DeepLDocumentNotFoundError {}
+
+/// MarkupLostError
+#[derive(Debug, Clone)]
+pub struct MarkupLostError;
+impl fmt::Display for MarkupLostError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "the translation lost some tags, try it again with --input-format text"
+        )
+    }
+}
+impl Error for MarkupLostError {}
+
+/// BatchLengthError
+#[derive(Debug, Clone)]
+pub struct BatchLengthError {
+    pub expected: usize,
+    pub got: usize,
+}
+impl fmt::Display for BatchLengthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "the translation has {} texts instead of {}",
+            self.got, self.expected
+        )
+    }
+}
+impl Error for BatchLengthError {}
+
+/// ProfileNotFoundError
+#[derive(Debug, Clone)]
+pub struct ProfileNotFoundError(pub String);
+impl fmt::Display for ProfileNotFoundError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "profile not found: {}", self.0)
+    }
+}
+impl Error for ProfileNotFoundError {}
@@ -0,0 +1,255 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::Mutex;
+
+use crate::errors::ExecEmptyCommandError;
+use crate::errors::ExecError;
+use crate::utils::SelectText;
+use crate::TranslateResult;
+use crate::TIMEOUT;
+
+/// The process of the persistent mode, it is restarted when the command changes or it exits
+static PERSISTENT: Mutex<Option<ExecProcess>> = Mutex::const_new(None);
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ExecSegment {
+    #[serde(default)]
+    pub orig: String,
+    pub trans: String,
+}
+
+/// One line of json from the command, `error` is set when the translation failed
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ExecResponse {
+    #[serde(default)]
+    pub segments: Vec<ExecSegment>,
+    #[serde(default)]
+    pub alternatives: Vec<String>,
+    /// The source language found by the command
+    pub detected_language: Option<String>,
+    pub error: Option<String>,
+}
+
+/// The options of the exec provider
+#[derive(Debug, Clone)]
+pub struct ExecOptions<'a> {
+    /// Run by the shell, so it may have arguments
+    pub command: &'a str,
+    /// Keep the process and send all the requests to it
+    pub persistent: bool,
+    /// `key=value` pairs passed to the command as they are
+    pub options: &'a [String],
+}
+
+struct ExecProcess {
+    command: String,
+    // killed on drop
+    _child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+fn spawn(command: &str) -> Result<ExecProcess> {
+    let mut cmd = match cfg!(target_os = "windows") {
+        true => {
+            let mut c = Command::new("cmd");
+            c.args(["/C", command]);
+            c
+        }
+        false => {
+            let mut c = Command::new("sh");
+            c.args(["-c", command]);
+            c
+        }
+    };
+    // the stderr of the command goes to the terminal for debugging
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let stdin = child
+        .stdin
+        .take()
+        .ok_or(ExecError("no stdin".to_string()))?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or(ExecError("no stdout".to_string()))?;
+    Ok(ExecProcess {
+        command: command.to_string(),
+        _child: child,
+        stdin,
+        stdout: BufReader::new(stdout),
+    })
+}
+
+/// Write one request line and read one response line
+async fn exchange(process: &mut ExecProcess, request: &serde_json::Value) -> Result<ExecResponse> {
+    let mut line = request.to_string();
+    line.push('\n');
+    process.stdin.write_all(line.as_bytes()).await?;
+    process.stdin.flush().await?;
+
+    let mut response = String::new();
+    let read = process.stdout.read_line(&mut response);
+    match tokio::time::timeout(Duration::from_secs(TIMEOUT), read).await {
+        Ok(Ok(0)) => Err(ExecError("the command exited without a response".to_string()).into()),
+        Ok(Ok(_)) => match serde_json::from_str::<ExecResponse>(&response) {
+            Ok(r) => Ok(r),
+            Err(e) => Err(ExecError(format!("invalid response: {}", e)).into()),
+        },
+        Ok(Err(e)) => Err(e.into()),
+        Err(_) => Err(ExecError("timeout".to_string()).into()),
+    }
+}
+
+fn build_request(sl: &str, tl: &str, text: &str, options: &ExecOptions) -> serde_json::Value {
+    let mut map = serde_json::Map::new();
+    for o in options.options {
+        let (k, v) = o.split_once('=').unwrap_or((o, ""));
+        map.insert(k.to_string(), json!(v));
+    }
+    json!({ "text": text, "sl": sl, "tl": tl, "options": map })
+}
+
+async fn request(
+    sl: &str,
+    tl: &str,
+    text: &str,
+    options: &ExecOptions<'_>,
+) -> Result<ExecResponse> {
+    if options.command == "null" || options.command.is_empty() {
+        return Err(ExecEmptyCommandError.into());
+    }
+    let request = build_request(sl, tl, text, options);
+    let res = match options.persistent {
+        true => {
+            let mut persistent = PERSISTENT.lock().await;
+            let mut process = match persistent.take() {
+                Some(p) if p.command == options.command => p,
+                _ => spawn(options.command)?,
+            };
+            let res = exchange(&mut process, &request).await;
+            // a broken process is dropped and the next request starts a new one
+            if res.is_ok() {
+                *persistent = Some(process);
+            }
+            res?
+        }
+        false => {
+            let mut process = spawn(options.command)?;
+            exchange(&mut process, &request).await?
+        }
+    };
+    match res.error {
+        Some(e) => Err(ExecError(e).into()),
+        None => Ok(res),
+    }
+}
+
+pub async fn translate_exec(
+    sl: &str, // source language
+    tl: &str, // target language
+    content: &str,
+    options: &ExecOptions<'_>,
+) -> Result<Vec<TranslateResult>> {
+    let content = SelectText::unescape(content);
+    let res = request(sl, tl, &content, options).await?;
+    let mut result_vec: Vec<TranslateResult> = res
+        .segments
+        .into_iter()
+        .map(|s| TranslateResult {
+            orig: s.orig,
+            trans: s.trans,
+            alter: Vec::new(),
+            dict: None,
+            romanization: None,
+            correction: None,
+            detected_lang: res.detected_language.clone(),
+        })
+        .collect();
+    // the alternatives are for the whole text, they are shown after the last segment
+    if let Some(last) = result_vec.last_mut() {
+        last.alter = res.alternatives;
+    }
+    Ok(result_vec)
+}
+
+pub async fn translate_batch_exec(
+    sl: &str, // source language
+    tl: &str, // target language
+    texts: &[String],
+    options: &ExecOptions<'_>,
+) -> Result<Vec<String>> {
+    let mut result_vec = Vec::new();
+    for t in texts {
+        let res = request(sl, tl, t, options).await?;
+        let trans: Vec<String> = res.segments.into_iter().map(|s| s.trans).collect();
+        result_vec.push(trans.concat());
+    }
+    Ok(result_vec)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    /// Echo the text back with a request counter, `fail` is answered with an error
+    const SCRIPT: &str = r#"n=0; while IFS= read -r line; do n=$((n+1)); t=${line#*\"text\":\"}; t=${t%%\"*}; if [ "$t" = fail ]; then printf '{"error":"no model"}\n'; else printf '{"segments":[{"orig":"%s","trans":"<%s>%d"}],"alternatives":["alt"],"detected_language":"en"}\n' "$t" "$t" "$n"; fi; done"#;
+
+    #[tokio::test]
+    async fn test_translate_exec() {
+        let extra = vec!["model=small".to_string()];
+        let options = ExecOptions {
+            command: SCRIPT,
+            persistent: false,
+            options: &extra,
+        };
+        assert_eq!(
+            build_request("en", "ja", "hi", &options).to_string(),
+            r#"{"text":"hi","sl":"en","tl":"ja","options":{"model":"small"}}"#
+        );
+        let r = translate_exec("en", "ja", "a%26b", &options).await.unwrap();
+        assert_eq!(r[0].orig, "a&b");
+        assert_eq!(r[0].trans, "<a&b>1");
+        assert_eq!(r[0].alter, vec!["alt"]);
+        assert_eq!(r[0].detected_lang.as_deref(), Some("en"));
+        // every request starts a new process
+        let r = translate_exec("en", "ja", "c", &options).await.unwrap();
+        assert_eq!(r[0].trans, "<c>1");
+
+        let e = translate_exec("en", "ja", "fail", &options)
+            .await
+            .unwrap_err();
+        assert_eq!(e.to_string(), "exec error: no model");
+
+        let options = ExecOptions {
+            command: "exit 0",
+            persistent: false,
+            options: &[],
+        };
+        assert!(translate_exec("en", "ja", "a", &options).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_persistent() {
+        let options = ExecOptions {
+            command: SCRIPT,
+            persistent: true,
+            options: &[],
+        };
+        let texts = vec!["a".to_string(), "b".to_string()];
+        let r = translate_batch_exec("en", "ja", &texts, &options)
+            .await
+            .unwrap();
+        assert_eq!(r, vec!["<a>1", "<b>2"]);
+        let r = translate_exec("en", "ja", "c", &options).await.unwrap();
+        assert_eq!(r[0].trans, "<c>3");
+    }
+}
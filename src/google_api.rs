@@ -1,12 +1,24 @@
-use crate::utils::{build_proxy, fliter_long, fliter_short};
+use crate::utils::{build_proxy, fliter_long, fliter_short, SelectText};
 use anyhow::Result;
-use reqwest;
-use serde_json;
 use std::time::Duration;
 
+use crate::errors::MarkupLostError;
+use crate::i18n::{protect, restore, split_markup};
+use crate::Correction;
+use crate::Definition;
+use crate::Dictionary;
+use crate::DictionaryEntry;
+use crate::ReverseTranslation;
+use crate::Romanization;
 use crate::TranslateResult;
 use crate::TIMEOUT;
 
+/// Keep the batch request far away from the url length limit of the gtx endpoint,
+/// it is the length of the percent-encoded `q` parameter
+const GOOGLE_MAX_BATCH_BYTES: usize = 1800;
+/// The endpoint of the batch requests
+const GOOGLE_BATCH_URL: &str = "https://translate.googleapis.com/translate_a/single";
+
 pub async fn translate_longstring(
     sl: &str, // source language
     tl: &str, // target language
@@ -15,7 +27,7 @@ pub async fn translate_longstring(
 ) -> Result<Vec<TranslateResult>> {
     let max_loop = 100;
     let translate_url = format!(
-        "https://translate.googleapis.com/translate_a/single?client=gtx&sl={}&tl={}&dt=t&q={}",
+        "https://translate.googleapis.com/translate_a/single?client=gtx&sl={}&tl={}&dt=t&dt=rm&q={}",
         sl,
         tl,
         fliter_long(content)
@@ -38,6 +50,7 @@ pub async fn translate_longstring(
 
     // println!("{:#?}", request_result);
     // [[["翻译","translate",null,null,10]],null,"en",null,null,null,null,[]]
+    let detected_lang = detected(sl, &request_result[2]);
     let mut i = 0;
     let mut result_vec: Vec<TranslateResult> = Vec::new();
     loop {
@@ -55,6 +68,10 @@ pub async fn translate_longstring(
                         trans: string_0,
                         orig: string_1,
                         alter: Vec::new(),
+                        dict: None,
+                        romanization: None,
+                        correction: None,
+                        detected_lang: detected_lang.clone(),
                     };
                     result_vec.push(item);
                 }
@@ -65,9 +82,105 @@ pub async fn translate_longstring(
             break;
         }
     }
+    // the transliteration of the whole text is the entry without a translation,
+    // `[null, null, "trans translit", "orig translit"]`
+    let rm = request_result[0]
+        .as_array()
+        .and_then(|a| a.iter().find(|s| s[0].is_null()));
+    if let (Some(rm), Some(last)) = (rm, result_vec.last_mut()) {
+        last.romanization = romanization(rm[3].as_str(), rm[2].as_str());
+    }
     Ok(result_vec)
 }
 
+fn romanization(orig: Option<&str>, trans: Option<&str>) -> Option<Romanization> {
+    let orig = orig.unwrap_or("").to_string();
+    let trans = trans.unwrap_or("").to_string();
+    match orig.is_empty() && trans.is_empty() {
+        true => None,
+        false => Some(Romanization { orig, trans }),
+    }
+}
+
+fn strings(value: &serde_json::Value) -> Vec<String> {
+    value
+        .as_array()
+        .map(|a| {
+            a.iter()
+                .filter_map(|v| v.as_str())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The entry of the part of speech, a new one is added at the end
+fn entry_of<'a>(entries: &'a mut Vec<DictionaryEntry>, pos: &str) -> &'a mut DictionaryEntry {
+    let i = match entries.iter().position(|e| e.pos == pos) {
+        Some(i) => i,
+        None => {
+            entries.push(DictionaryEntry {
+                pos: pos.to_string(),
+                ..Default::default()
+            });
+            entries.len() - 1
+        }
+    };
+    &mut entries[i]
+}
+
+/// The `dict` (bd), `definitions` (md), `synsets` (ss) and `examples` (ex) of the response
+fn dictionary(result: &serde_json::Value) -> Option<Dictionary> {
+    let list = |key: &str| result[key].as_array().cloned().unwrap_or_default();
+    let mut entries = Vec::new();
+    for d in list("dict") {
+        let entry = entry_of(&mut entries, d["pos"].as_str().unwrap_or(""));
+        for t in d["entry"].as_array().into_iter().flatten() {
+            if let Some(word) = t["word"].as_str() {
+                entry.translations.push(ReverseTranslation {
+                    word: word.to_string(),
+                    reverse: strings(&t["reverse_translation"]),
+                });
+            }
+        }
+    }
+    for d in list("definitions") {
+        let entry = entry_of(&mut entries, d["pos"].as_str().unwrap_or(""));
+        for g in d["entry"].as_array().into_iter().flatten() {
+            if let Some(gloss) = g["gloss"].as_str() {
+                entry.definitions.push(Definition {
+                    gloss: gloss.to_string(),
+                    example: g["example"].as_str().map(String::from),
+                });
+            }
+        }
+    }
+    for d in list("synsets") {
+        let entry = entry_of(&mut entries, d["pos"].as_str().unwrap_or(""));
+        for s in d["entry"].as_array().into_iter().flatten() {
+            for synonym in strings(&s["synonym"]) {
+                if !entry.synonyms.contains(&synonym) {
+                    entry.synonyms.push(synonym);
+                }
+            }
+        }
+    }
+    let examples: Vec<String> = result["examples"]["example"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e["text"].as_str().map(String::from))
+        .collect();
+    match entries.is_empty() && examples.is_empty() {
+        true => None,
+        false => Some(Dictionary {
+            entries,
+            examples,
+            ..Default::default()
+        }),
+    }
+}
+
 pub async fn translate_shortword(
     sl: &str,
     tl: &str,
@@ -75,7 +188,7 @@ pub async fn translate_shortword(
     proxy_str: &str,
 ) -> Result<Vec<TranslateResult>> {
     let translate_url = format!(
-        "https://translate.googleapis.com/translate_a/single?client=gtx&sl={}&tl={}&dj=1&dt=t&dt=bd&dt=qc&dt=rm&dt=ex&dt=at&dt=ss&dt=rw&dt=ld&q={}&button&tk=233819.233819",
+        "https://translate.googleapis.com/translate_a/single?client=gtx&sl={}&tl={}&dj=1&dt=t&dt=bd&dt=md&dt=qc&dt=rm&dt=ex&dt=at&dt=ss&dt=rw&dt=ld&q={}&button&tk=233819.233819",
         sl, tl, fliter_short(translate_string)
     );
     let proxy = build_proxy(proxy_str);
@@ -115,11 +228,7 @@ pub async fn translate_shortword(
         .unwrap();
     let mut i = 0;
     let mut alter = Vec::new();
-    loop {
-        let wp = match alter_vec[i].get("word_postproc") {
-            Some(a) => a,
-            _ => break,
-        };
+    while let Some(wp) = alter_vec[i].get("word_postproc") {
         // jump the first word
         if i != 0 {
             let alter_string = format!("{}", wp);
@@ -130,7 +239,363 @@ pub async fn translate_shortword(
     }
     let trans = trans.replace("\"", "");
     let orig = orig.replace("\"", "");
-    let item = TranslateResult { trans, orig, alter };
+    // `{"translit": "Píngguǒ", "src_translit": "..."}` follows the sentence
+    let romanization = request_result["sentences"]
+        .as_array()
+        .and_then(|a| a.iter().find(|s| s.get("trans").is_none()))
+        .and_then(|rm| romanization(rm["src_translit"].as_str(), rm["translit"].as_str()));
+    let dict = dictionary(&request_result);
+    let correction = correction(&request_result, &orig, dict.is_none());
+    let item = TranslateResult {
+        trans,
+        orig,
+        alter,
+        dict,
+        romanization,
+        correction,
+        detected_lang: detected(sl, &request_result["src"]),
+    };
     result_vec.push(item);
     Ok(result_vec)
 }
+
+/// The language google found for `-s auto`
+fn detected(sl: &str, src: &serde_json::Value) -> Option<String> {
+    match sl {
+        "auto" => src.as_str().map(String::from),
+        _ => None,
+    }
+}
+
+/// The `spell` (qc) of the response, `{"spell_html_res": "<b><i>hello</i></b>", "spell_res": "hello"}`,
+/// a word without a dictionary entry may be a typo, `--auto-correct` still checks the corrected one
+fn correction(result: &serde_json::Value, orig: &str, unknown: bool) -> Option<Correction> {
+    let text = result["spell"]["spell_res"].as_str()?.trim();
+    match text.is_empty() || text.eq_ignore_ascii_case(orig.trim()) {
+        true => None,
+        false => Some(Correction {
+            orig: orig.to_string(),
+            text: text.to_string(),
+            confident: unknown,
+            applied: false,
+        }),
+    }
+}
+
+/// Translate html or xml, the tags are replaced with `{0}`, `{1}`... which google
+/// leaves alone, and put back after the translation
+pub async fn translate_markup(
+    sl: &str, // source language
+    tl: &str, // target language
+    content: &str,
+    proxy_str: &str,
+) -> Result<Vec<TranslateResult>> {
+    let orig = SelectText::unescape(content);
+    let pieces = split_markup(&orig);
+    let q = protect(&pieces);
+    let proxy = build_proxy(proxy_str);
+    let client = match proxy {
+        Some(p) => reqwest::Client::builder()
+            .proxy(p)
+            .build()
+            .expect("proxy client build failed"),
+        _ => reqwest::Client::new(),
+    };
+    let request_result = client
+        .get("https://translate.googleapis.com/translate_a/single")
+        .query(&[
+            ("client", "gtx"),
+            ("sl", sl),
+            ("tl", tl),
+            ("dt", "t"),
+            ("q", &q),
+        ])
+        .timeout(Duration::from_secs(TIMEOUT))
+        .send()
+        .await?
+        .json::<serde_json::Value>()
+        .await?;
+
+    // one result, a token may be moved to another sentence
+    let mut trans = String::new();
+    if let Some(sentences) = request_result[0].as_array() {
+        for s in sentences {
+            if let Some(t) = s[0].as_str() {
+                trans.push_str(t);
+            }
+        }
+    }
+    match restore(&trans, &pieces) {
+        Some(trans) => Ok(vec![TranslateResult {
+            orig,
+            trans,
+            alter: Vec::new(),
+            dict: None,
+            romanization: None,
+            correction: None,
+            detected_lang: detected(sl, &request_result[2]),
+        }]),
+        None => Err(MarkupLostError.into()),
+    }
+}
+
+async fn translate_lines(
+    client: &reqwest::Client,
+    url: &str,
+    sl: &str,
+    tl: &str,
+    lines: &[String],
+) -> Result<Vec<String>> {
+    let q = lines.join("\n");
+    let request_result = client
+        .get(url)
+        .query(&[
+            ("client", "gtx"),
+            ("sl", sl),
+            ("tl", tl),
+            ("dt", "t"),
+            ("q", &q),
+        ])
+        .timeout(Duration::from_secs(TIMEOUT))
+        .send()
+        .await?
+        .json::<serde_json::Value>()
+        .await?;
+
+    // the newlines are kept in the translated sentences
+    let mut trans = String::new();
+    if let Some(sentences) = request_result[0].as_array() {
+        for s in sentences {
+            if let Some(t) = s[0].as_str() {
+                trans.push_str(t);
+            }
+        }
+    }
+    Ok(trans.lines().map(|l| l.trim().to_string()).collect())
+}
+
+/// Translate many texts with as few requests as possible
+pub async fn translate_batch(
+    sl: &str,
+    tl: &str,
+    texts: &[String],
+    proxy_str: &str,
+) -> Result<Vec<String>> {
+    let proxy = build_proxy(proxy_str);
+    let client = match proxy {
+        Some(p) => reqwest::Client::builder()
+            .proxy(p)
+            .build()
+            .expect("proxy client build failed"),
+        _ => reqwest::Client::new(),
+    };
+    translate_batch_at(&client, GOOGLE_BATCH_URL, sl, tl, texts).await
+}
+
+/// Length of the text in the query string, a byte takes three characters once encoded
+fn encoded_len(text: &str) -> usize {
+    text.bytes()
+        .map(
+            |b| match b.is_ascii_alphanumeric() || b"*-._ ".contains(&b) {
+                true => 1,
+                false => 3,
+            },
+        )
+        .sum()
+}
+
+/// Every line of the texts is sent as one line of a size-bounded request,
+/// the lines of a multi-line text are joined again after the translation
+async fn translate_batch_at(
+    client: &reqwest::Client,
+    url: &str,
+    sl: &str,
+    tl: &str,
+    texts: &[String],
+) -> Result<Vec<String>> {
+    // google drops the blank lines, they are kept as they are
+    let lines: Vec<String> = texts
+        .iter()
+        .flat_map(|t| t.split('\n'))
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.to_string())
+        .collect();
+
+    let mut trans_lines = Vec::new();
+    let mut start = 0;
+    while start < lines.len() {
+        let mut end = start;
+        let mut batch_bytes = 0;
+        while end < lines.len() {
+            // the line and the encoded newline after it
+            batch_bytes += encoded_len(&lines[end]) + 3;
+            if end > start && batch_bytes > GOOGLE_MAX_BATCH_BYTES {
+                break;
+            }
+            end += 1;
+        }
+        let batch = &lines[start..end];
+        let trans = translate_lines(client, url, sl, tl, batch).await?;
+        if trans.len() == batch.len() {
+            trans_lines.extend(trans);
+        } else {
+            // google merged or split some lines, translate them one by one
+            for l in batch {
+                let trans = translate_lines(client, url, sl, tl, std::slice::from_ref(l)).await?;
+                trans_lines.push(trans.join(" "));
+            }
+        }
+        start = end;
+    }
+
+    let mut trans_lines = trans_lines.into_iter();
+    let result_vec = texts
+        .iter()
+        .map(|t| {
+            t.split('\n')
+                .map(|l| match l.trim().is_empty() {
+                    true => l.to_string(),
+                    false => trans_lines.next().unwrap_or_default(),
+                })
+                .collect::<Vec<String>>()
+                .join("\n")
+        })
+        .collect();
+    Ok(result_vec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::HttpResponse;
+    use crate::test_support::{mock_server, percent_decode};
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    /// Upper-cases every line of `q` like a translation, the encoded queries are kept
+    async fn gtx_server(queries: Arc<Mutex<Vec<String>>>) -> String {
+        mock_server(move |request| {
+            let raw = request.param("q").unwrap_or("").to_string();
+            let q = percent_decode(&raw);
+            let sentences: Vec<_> = q
+                .split('\n')
+                .enumerate()
+                .map(|(i, l)| match i {
+                    0 => json!([l.to_uppercase()]),
+                    _ => json!([format!("\n{}", l.to_uppercase())]),
+                })
+                .collect();
+            queries.lock().unwrap().push(raw);
+            HttpResponse::json(200, json!([sentences]))
+        })
+        .await
+    }
+
+    #[test]
+    fn test_dictionary() {
+        let result = json!({
+            "sentences": [{ "trans": "苹果", "orig": "apple" }],
+            "dict": [{
+                "pos": "noun",
+                "terms": ["苹果", "苹果树"],
+                "entry": [
+                    { "word": "苹果", "reverse_translation": ["apple"], "score": 0.6 },
+                    { "word": "苹果树", "reverse_translation": ["apple", "apple tree"] }
+                ]
+            }],
+            "definitions": [
+                { "pos": "noun", "entry": [{ "gloss": "the round fruit of a tree", "example": "apple juice" }] },
+                { "pos": "adjective", "entry": [{ "gloss": "of the color of apples" }] }
+            ],
+            "synsets": [{ "pos": "noun", "entry": [{ "synonym": ["pome"] }, { "synonym": ["pome", "false fruit"] }] }],
+            "examples": { "example": [{ "text": "an <b>apple</b> pie", "source_type": 3 }] }
+        });
+        let dict = dictionary(&result).unwrap();
+        assert_eq!(dict.entries.len(), 2);
+        let noun = &dict.entries[0];
+        assert_eq!(noun.pos, "noun");
+        assert_eq!(noun.translations[1].reverse, vec!["apple", "apple tree"]);
+        assert_eq!(noun.definitions[0].example.as_deref(), Some("apple juice"));
+        assert_eq!(noun.synonyms, vec!["pome", "false fruit"]);
+        assert_eq!(dict.entries[1].pos, "adjective");
+        assert!(dict.entries[1].translations.is_empty());
+        assert_eq!(dict.examples, vec!["an <b>apple</b> pie"]);
+
+        assert!(dictionary(&json!({ "sentences": [] })).is_none());
+    }
+
+    #[test]
+    fn test_romanization() {
+        let r = romanization(None, Some("Píngguǒ")).unwrap();
+        assert_eq!((r.orig.as_str(), r.trans.as_str()), ("", "Píngguǒ"));
+        assert_eq!(r.text(), "Píngguǒ");
+        let r = romanization(Some("nihon"), Some("Rìběn")).unwrap();
+        assert_eq!(r.text(), "nihon → Rìběn");
+        assert!(romanization(Some(""), None).is_none());
+    }
+
+    #[test]
+    fn test_correction() {
+        let result = json!({
+            "sentences": [{ "trans": "你好", "orig": "helo" }],
+            "spell": { "spell_html_res": "<b><i>hello</i></b>", "spell_res": "hello", "correction_type": [1] }
+        });
+        let c = correction(&result, "helo", true).unwrap();
+        assert_eq!((c.orig.as_str(), c.text.as_str()), ("helo", "hello"));
+        assert!(c.confident && !c.applied);
+        assert!(!correction(&result, "helo", false).unwrap().confident);
+        // only the casing differs
+        assert!(correction(&result, "Hello", true).is_none());
+        assert!(correction(&json!({ "spell": {} }), "hello", true).is_none());
+    }
+
+    #[test]
+    fn test_detected() {
+        assert_eq!(detected("auto", &json!("fr")).as_deref(), Some("fr"));
+        // the given source language is not a detection
+        assert!(detected("en", &json!("en")).is_none());
+        assert!(detected("auto", &json!(null)).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_batch_lines() {
+        let queries = Arc::new(Mutex::new(Vec::new()));
+        let url = gtx_server(queries.clone()).await;
+        let client = reqwest::Client::new();
+        let texts = vec![
+            "Usage:\n  %s FILE".to_string(),
+            "one line".to_string(),
+            "a\n\nb".to_string(),
+        ];
+        let r = translate_batch_at(&client, &url, "en", "de", &texts)
+            .await
+            .unwrap();
+        assert_eq!(r, vec!["USAGE:\n%S FILE", "ONE LINE", "A\n\nB"]);
+        // every line is sent once in a single request, the blank one is not sent
+        let queries = queries.lock().unwrap();
+        assert_eq!(queries.len(), 1);
+        assert_eq!(
+            percent_decode(&queries[0]),
+            "Usage:\n  %s FILE\none line\na\nb"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_batch_size() {
+        assert_eq!(encoded_len("a b-c"), 5);
+        assert_eq!(encoded_len("é?"), 9);
+
+        let queries = Arc::new(Mutex::new(Vec::new()));
+        let url = gtx_server(queries.clone()).await;
+        let client = reqwest::Client::new();
+        // 1100 characters, but more than 9000 once encoded
+        let texts = vec!["日本語のテキストです".to_string(); 100];
+        let r = translate_batch_at(&client, &url, "ja", "en", &texts)
+            .await
+            .unwrap();
+        assert_eq!(r, texts);
+        let queries = queries.lock().unwrap();
+        assert!(queries.len() > 1);
+        assert!(queries.iter().all(|q| q.len() <= GOOGLE_MAX_BATCH_BYTES));
+    }
+}
@@ -0,0 +1,275 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::errors::GoogleCloudEmptyAuthKeyError;
+use crate::errors::GoogleCloudError;
+use crate::utils::{build_client, SelectText};
+use crate::TranslateResult;
+use crate::TIMEOUT;
+
+const GOOGLECLOUD_URL: &str = "https://translation.googleapis.com/language/translate/v2";
+/// The v2 api accepts at most 128 `q` and 30k chars in one request
+const GOOGLECLOUD_MAX_TEXTS: usize = 128;
+const GOOGLECLOUD_MAX_BATCH_CHARS: usize = 25000;
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GoogleCloudTranslation {
+    pub translated_text: String,
+    pub detected_source_language: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GoogleCloudTranslations {
+    pub translations: Vec<GoogleCloudTranslation>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GoogleCloudResponse {
+    pub data: GoogleCloudTranslations,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GoogleCloudLanguage {
+    pub language: String,
+    #[serde(default)]
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GoogleCloudLanguages {
+    pub languages: Vec<GoogleCloudLanguage>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GoogleCloudLanguagesResponse {
+    pub data: GoogleCloudLanguages,
+}
+
+fn build_url(endpoint: &str, path: &str) -> String {
+    let endpoint = match endpoint {
+        "null" => GOOGLECLOUD_URL,
+        _ => endpoint.trim_end_matches('/'),
+    };
+    format!("{}{}", endpoint, path)
+}
+
+/// The error body is `{"error": {"code": 400, "message": "..."}}`
+async fn check_response(res: reqwest::Response) -> Result<reqwest::Response> {
+    if res.status().is_success() {
+        return Ok(res);
+    }
+    let status = res.status().as_u16();
+    let message = match res.json::<serde_json::Value>().await {
+        Ok(v) => v["error"]["message"]
+            .as_str()
+            .unwrap_or("unknown error")
+            .to_string(),
+        Err(_) => "unknown error".to_string(),
+    };
+    Err(GoogleCloudError(format!("{} ({})", message, status)).into())
+}
+
+async fn request(
+    sl: &str,
+    tl: &str,
+    texts: &[&str],
+    format: &str,
+    proxy_str: &str,
+    auth_key: &str,
+    endpoint: &str,
+) -> Result<Vec<GoogleCloudTranslation>> {
+    if auth_key == "null" || auth_key.is_empty() {
+        return Err(GoogleCloudEmptyAuthKeyError.into());
+    }
+    // google cloud has no xml format, the html one keeps the xml tags as well
+    let format = match format {
+        "xml" => "html",
+        f => f,
+    };
+    let mut params = vec![("target", tl), ("format", format)];
+    // the source language is detected when it is missing
+    if sl != "auto" {
+        params.push(("source", sl));
+    }
+    for t in texts {
+        params.push(("q", t));
+    }
+
+    let client = build_client(proxy_str);
+    let res = client
+        .post(build_url(endpoint, ""))
+        .query(&[("key", auth_key)])
+        .form(&params)
+        .timeout(Duration::from_secs(TIMEOUT))
+        .send()
+        .await?;
+    let res = check_response(res)
+        .await?
+        .json::<GoogleCloudResponse>()
+        .await?;
+    Ok(res.data.translations)
+}
+
+/// The languages supported by the api, the names are in the target language
+pub async fn languages(
+    tl: &str,
+    proxy_str: &str,
+    auth_key: &str,
+    endpoint: &str,
+) -> Result<Vec<GoogleCloudLanguage>> {
+    if auth_key == "null" || auth_key.is_empty() {
+        return Err(GoogleCloudEmptyAuthKeyError.into());
+    }
+    let client = build_client(proxy_str);
+    let res = client
+        .get(build_url(endpoint, "/languages"))
+        .query(&[("key", auth_key), ("target", tl)])
+        .timeout(Duration::from_secs(TIMEOUT))
+        .send()
+        .await?;
+    let res = check_response(res)
+        .await?
+        .json::<GoogleCloudLanguagesResponse>()
+        .await?;
+    Ok(res.data.languages)
+}
+
+pub async fn translate_googlecloud(
+    sl: &str, // source language
+    tl: &str, // target language
+    content: &str,
+    format: &str,
+    proxy_str: &str,
+    auth_key: &str,
+    endpoint: &str,
+) -> Result<Vec<TranslateResult>> {
+    let content = SelectText::unescape(content);
+    let res = request(sl, tl, &[&content], format, proxy_str, auth_key, endpoint).await?;
+    let trans = match res.into_iter().next() {
+        Some(t) => t.translated_text,
+        None => String::new(),
+    };
+    let item = TranslateResult {
+        orig: content,
+        trans,
+        alter: Vec::new(),
+        dict: None,
+        romanization: None,
+        correction: None,
+        detected_lang: None,
+    };
+    Ok(vec![item])
+}
+
+pub async fn translate_batch_googlecloud(
+    sl: &str, // source language
+    tl: &str, // target language
+    texts: &[String],
+    format: &str,
+    proxy_str: &str,
+    auth_key: &str,
+    endpoint: &str,
+) -> Result<Vec<String>> {
+    let mut result_vec = Vec::new();
+    let mut start = 0;
+    while start < texts.len() {
+        let mut end = start;
+        let mut batch_chars = 0;
+        while end < texts.len() && end - start < GOOGLECLOUD_MAX_TEXTS {
+            batch_chars += texts[end].chars().count();
+            if end > start && batch_chars > GOOGLECLOUD_MAX_BATCH_CHARS {
+                break;
+            }
+            end += 1;
+        }
+        let batch: Vec<&str> = texts[start..end].iter().map(|t| t.as_str()).collect();
+        let res = request(sl, tl, &batch, format, proxy_str, auth_key, endpoint).await?;
+        result_vec.extend(res.into_iter().map(|t| t.translated_text));
+        start = end;
+    }
+    Ok(result_vec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::HttpResponse;
+    use crate::test_support::mock_server;
+    use serde_json::json;
+
+    async fn googlecloud_server() -> String {
+        mock_server(|request| {
+            if request.param("key") != Some("key") {
+                return HttpResponse::json(
+                    400,
+                    json!({ "error": { "code": 400, "message": "API key not valid." } }),
+                );
+            }
+            if request.path() == "/languages" {
+                return HttpResponse::json(
+                    200,
+                    json!({ "data": { "languages": [{ "language": "en", "name": "English" }] } }),
+                );
+            }
+            let form = request.form();
+            let param = |name: &str| request.form_param(name);
+            let source = match param("source").as_str() {
+                "" => "detected".to_string(),
+                s => s.to_string(),
+            };
+            let translations: Vec<serde_json::Value> = form
+                .iter()
+                .filter(|(k, _)| k == "q")
+                .map(|(_, q)| {
+                    let text = format!("{}>{}:{}:{}", source, param("target"), param("format"), q);
+                    json!({ "translatedText": text, "detectedSourceLanguage": "en" })
+                })
+                .collect();
+            HttpResponse::json(200, json!({ "data": { "translations": translations } }))
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_translate_googlecloud() {
+        let url = googlecloud_server().await;
+        let r = translate_googlecloud("en", "ja", "word", "text", "null", "key", &url)
+            .await
+            .unwrap();
+        assert_eq!(r[0].trans, "en>ja:text:word");
+
+        let r = translate_googlecloud("auto", "ja", "<b>word</b>", "html", "null", "key", &url)
+            .await
+            .unwrap();
+        assert_eq!(r[0].trans, "detected>ja:html:<b>word</b>");
+
+        let texts = vec!["a".to_string(), "b".to_string()];
+        let r = translate_batch_googlecloud("en", "ja", &texts, "text", "null", "key", &url)
+            .await
+            .unwrap();
+        assert_eq!(r, vec!["en>ja:text:a", "en>ja:text:b"]);
+    }
+
+    #[tokio::test]
+    async fn test_languages_and_error() {
+        let url = googlecloud_server().await;
+        let langs = languages("en", "null", "key", &url).await.unwrap();
+        assert_eq!(langs[0].language, "en");
+        assert_eq!(langs[0].name, "English");
+
+        let e = translate_googlecloud("en", "ja", "word", "text", "null", "wrong", &url)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "google cloud error: API key not valid. (400)"
+        );
+
+        let e = translate_googlecloud("en", "ja", "word", "text", "null", "null", &url)
+            .await
+            .unwrap_err();
+        assert!(e.downcast_ref::<GoogleCloudEmptyAuthKeyError>().is_some());
+    }
+}
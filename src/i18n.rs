@@ -0,0 +1,497 @@
+use anyhow::Result;
+use colored::Colorize;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+use crate::errors::UnsupportResourceError;
+use crate::translate_texts;
+use crate::utils::extract_placeholders;
+use crate::Args;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResourceFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl ResourceFormat {
+    fn from_path(path: &Path) -> Result<ResourceFormat> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Ok(ResourceFormat::Json),
+            Some("yaml") | Some("yml") => Ok(ResourceFormat::Yaml),
+            Some("toml") => Ok(ResourceFormat::Toml),
+            _ => Err(UnsupportResourceError.into()),
+        }
+    }
+    fn load(&self, content: &str) -> Result<Value> {
+        let value = match self {
+            ResourceFormat::Json => serde_json::from_str(content)?,
+            ResourceFormat::Yaml => serde_yaml::from_str(content)?,
+            ResourceFormat::Toml => toml::from_str(content)?,
+        };
+        Ok(value)
+    }
+    fn dump(&self, value: &Value) -> Result<String> {
+        let content = match self {
+            ResourceFormat::Json => serde_json::to_string_pretty(value)? + "\n",
+            ResourceFormat::Yaml => serde_yaml::to_string(value)?,
+            ResourceFormat::Toml => toml::to_string_pretty(value)?,
+        };
+        Ok(content)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Piece {
+    Text(String),
+    /// ICU syntax and interpolation tokens, never sent to the translator
+    Keep(String),
+}
+
+/// Index of the brace which closes the one at `start`
+fn matching_brace(chars: &[char], start: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, &c) in chars.iter().enumerate().skip(start) {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+fn push_keep(pieces: &mut Vec<Piece>, keep: &str) {
+    match pieces.last_mut() {
+        Some(Piece::Keep(k)) => k.push_str(keep),
+        _ => pieces.push(Piece::Keep(keep.to_string())),
+    }
+}
+
+fn push_text(pieces: &mut Vec<Piece>, c: char) {
+    match pieces.last_mut() {
+        Some(Piece::Text(t)) => t.push(c),
+        _ => pieces.push(Piece::Text(c.to_string())),
+    }
+}
+
+/// Split an ICU argument `{count, plural, one {# item} other {# items}}`,
+/// only the sub-messages are translatable
+fn split_icu_argument(inner: &str, pieces: &mut Vec<Piece>) {
+    let fields: Vec<&str> = inner.splitn(3, ',').collect();
+    let kind = fields.get(1).map(|k| k.trim()).unwrap_or("");
+    if fields.len() < 3 || !["plural", "select", "selectordinal"].contains(&kind) {
+        push_keep(pieces, &format!("{{{}}}", inner));
+        return;
+    }
+    push_keep(pieces, &format!("{{{},{},", fields[0], fields[1]));
+    let chars: Vec<char> = fields[2].chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] == '{' {
+            match matching_brace(&chars, i) {
+                Some(end) => {
+                    push_keep(pieces, "{");
+                    let sub: String = chars[i + 1..end].iter().collect();
+                    split_message_into(&sub, true, pieces);
+                    push_keep(pieces, "}");
+                    i = end + 1;
+                }
+                None => {
+                    let rest: String = chars[i..].iter().collect();
+                    push_keep(pieces, &rest);
+                    break;
+                }
+            }
+        } else {
+            // selector keywords like `one`, `=0` and `offset:1`
+            push_keep(pieces, &chars[i].to_string());
+            i += 1;
+        }
+    }
+    push_keep(pieces, "}");
+}
+
+fn split_message_into(message: &str, in_plural: bool, pieces: &mut Vec<Piece>) {
+    let chars: Vec<char> = message.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let rest: String = chars[i..].iter().collect();
+        if chars[i] == '{' && rest.starts_with("{{") {
+            // {{name}} of i18next, vue-i18n and handlebars
+            match rest.find("}}") {
+                Some(end) => {
+                    push_keep(pieces, &rest[..end + 2]);
+                    i += rest[..end + 2].chars().count();
+                }
+                None => {
+                    push_keep(pieces, &rest);
+                    break;
+                }
+            }
+        } else if chars[i] == '{' {
+            match matching_brace(&chars, i) {
+                Some(end) => {
+                    let inner: String = chars[i + 1..end].iter().collect();
+                    split_icu_argument(&inner, pieces);
+                    i = end + 1;
+                }
+                None => {
+                    push_keep(pieces, &rest);
+                    break;
+                }
+            }
+        } else if chars[i] == '%' && rest.starts_with("%{") {
+            // %{name} of rails
+            let end = rest.find('}').unwrap_or(rest.len() - 1);
+            push_keep(pieces, &rest[..end + 1]);
+            i += rest[..end + 1].chars().count();
+        } else if chars[i] == '%' {
+            let printf = extract_placeholders(&rest)
+                .into_iter()
+                .find(|p| p.starts_with('%') && rest.starts_with(p.as_str()));
+            match printf {
+                Some(p) => {
+                    push_keep(pieces, &p);
+                    i += p.chars().count();
+                }
+                None => {
+                    push_text(pieces, '%');
+                    i += 1;
+                }
+            }
+        } else if chars[i] == '#' && in_plural {
+            push_keep(pieces, "#");
+            i += 1;
+        } else {
+            push_text(pieces, chars[i]);
+            i += 1;
+        }
+    }
+}
+
+pub fn split_message(message: &str) -> Vec<Piece> {
+    let mut pieces = Vec::new();
+    split_message_into(message, false, &mut pieces);
+    pieces
+}
+
+/// Split html or xml, the tags, the comments and the entities are kept, the braces
+/// too so they can not be taken for the `{0}` tokens of `protect`
+pub fn split_markup(markup: &str) -> Vec<Piece> {
+    let mut pieces = Vec::new();
+    let mut rest = markup;
+    while let Some(c) = rest.chars().next() {
+        let end = match c {
+            '<' if rest.starts_with("<!--") => rest.find("-->").map(|e| e + 3),
+            '<' if rest.starts_with("<![CDATA[") => rest.find("]]>").map(|e| e + 3),
+            '<' => rest.find('>').map(|e| e + 1),
+            '&' => rest
+                .find(';')
+                .filter(|e| {
+                    *e > 1
+                        && rest[1..*e]
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '#')
+                })
+                .map(|e| e + 1),
+            '{' | '}' => Some(1),
+            _ => None,
+        };
+        match end {
+            Some(end) => {
+                push_keep(&mut pieces, &rest[..end]);
+                rest = &rest[end..];
+            }
+            None => {
+                push_text(&mut pieces, c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    pieces
+}
+
+/// Replace the kept pieces with `{0}`, `{1}`... which the translators leave alone
+pub fn protect(pieces: &[Piece]) -> String {
+    let mut index = 0;
+    let mut output = String::new();
+    for p in pieces {
+        match p {
+            Piece::Text(t) => output.push_str(t),
+            Piece::Keep(_) => {
+                output.push_str(&format!("{{{}}}", index));
+                index += 1;
+            }
+        }
+    }
+    output
+}
+
+/// Put the kept pieces back, None if the translator lost or duplicated a token
+pub fn restore(trans: &str, pieces: &[Piece]) -> Option<String> {
+    let keeps: Vec<&String> = pieces
+        .iter()
+        .filter_map(|p| match p {
+            Piece::Keep(k) => Some(k),
+            _ => None,
+        })
+        .collect();
+    let mut used = vec![0; keeps.len()];
+    let mut output = String::new();
+    let mut rest = trans;
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let token = rest
+            .find('}')
+            .and_then(|end| rest[1..end].parse::<usize>().ok().map(|i| (i, end)));
+        match token {
+            Some((i, end)) if i < keeps.len() => {
+                output.push_str(keeps[i]);
+                used[i] += 1;
+                rest = &rest[end + 1..];
+            }
+            _ => {
+                output.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    output.push_str(rest);
+    match used.iter().all(|&u| u == 1) {
+        true => Some(output),
+        false => None,
+    }
+}
+
+fn need_translate(pieces: &[Piece]) -> bool {
+    pieces.iter().any(|p| match p {
+        Piece::Text(t) => t.chars().any(|c| c.is_alphabetic()),
+        _ => false,
+    })
+}
+
+/// Collect the string leaves which should be translated, the path of every leaf
+/// is a list of object keys and array indexes
+fn collect_leaves(
+    value: &Value,
+    target: Option<&Value>,
+    path: &mut Vec<String>,
+    leaves: &mut Vec<Vec<String>>,
+) {
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map {
+                path.push(k.clone());
+                collect_leaves(v, target.and_then(|t| t.get(k)), path, leaves);
+                path.pop();
+            }
+        }
+        Value::Array(array) => {
+            for (i, v) in array.iter().enumerate() {
+                path.push(i.to_string());
+                collect_leaves(v, target.and_then(|t| t.get(i)), path, leaves);
+                path.pop();
+            }
+        }
+        Value::String(s) => {
+            let exists = matches!(target, Some(Value::String(_)));
+            if !exists && need_translate(&split_message(s)) {
+                leaves.push(path.clone());
+            }
+        }
+        _ => (),
+    }
+}
+
+fn get_mut<'a>(value: &'a mut Value, path: &[String]) -> Option<&'a mut Value> {
+    let mut current = value;
+    for key in path {
+        current = match current {
+            Value::Object(map) => map.get_mut(key)?,
+            Value::Array(array) => array.get_mut(key.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(current)
+}
+
+fn get<'a>(value: &'a Value, path: &[String]) -> Option<&'a Value> {
+    let mut current = value;
+    for key in path {
+        current = match current {
+            Value::Object(map) => map.get(key)?,
+            Value::Array(array) => array.get(key.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(current)
+}
+
+/// Existing translations of the target file are copied into the source tree,
+/// so the output always follows the keys and the ordering of the source file
+fn merge_existing(value: &mut Value, target: &Value) {
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map.iter_mut() {
+                if let Some(t) = target.get(k) {
+                    merge_existing(v, t);
+                }
+            }
+        }
+        Value::Array(array) => {
+            for (i, v) in array.iter_mut().enumerate() {
+                if let Some(t) = target.get(i) {
+                    merge_existing(v, t);
+                }
+            }
+        }
+        Value::String(s) => {
+            if let Value::String(t) = target {
+                *s = t.clone();
+            }
+        }
+        _ => (),
+    }
+}
+
+pub async fn translate_resource_file(
+    sl: &str,
+    tl: &str,
+    input: &str,
+    output: &str,
+    incremental: bool,
+    args: &Args,
+) -> Result<()> {
+    let path = Path::new(input);
+    let format = ResourceFormat::from_path(path)?;
+    let mut value = format.load(&fs::read_to_string(path)?)?;
+
+    let output = match output {
+        "null" => {
+            let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+            let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("json");
+            // en.json => ja.json, messages.json => messages.ja.json
+            match stem.eq_ignore_ascii_case(sl) {
+                true => path.with_file_name(format!("{}.{}", tl, ext)),
+                false => path.with_file_name(format!("{}.{}.{}", stem, tl, ext)),
+            }
+        }
+        _ => Path::new(output).to_path_buf(),
+    };
+    let target = match incremental && output.exists() {
+        true => Some(ResourceFormat::from_path(&output)?.load(&fs::read_to_string(&output)?)?),
+        false => None,
+    };
+
+    let mut leaves = Vec::new();
+    collect_leaves(&value, target.as_ref(), &mut Vec::new(), &mut leaves);
+    let pieces: Vec<Vec<Piece>> = leaves
+        .iter()
+        .map(|l| match get(&value, l) {
+            Some(Value::String(s)) => split_message(s),
+            _ => Vec::new(),
+        })
+        .collect();
+    let texts: Vec<String> = pieces.iter().map(|p| protect(p)).collect();
+    println!(
+        "{}{}{}",
+        "Translating ".green(),
+        format!("{} strings", texts.len()).green().bold(),
+        "...".green()
+    );
+    let trans = translate_texts(sl, tl, &texts, args).await?;
+
+    if let Some(t) = &target {
+        merge_existing(&mut value, t);
+    }
+    let mut failed = 0;
+    for ((leaf, p), t) in leaves.iter().zip(pieces.iter()).zip(trans.iter()) {
+        match (restore(t, p), get_mut(&mut value, leaf)) {
+            (Some(r), Some(v)) => *v = Value::String(r),
+            _ => {
+                // keep the source string, it is better than a broken message
+                println!("{} {}", "untranslated:".yellow(), leaf.join("."));
+                failed += 1;
+            }
+        }
+    }
+
+    fs::write(&output, format.dump(&value)?)?;
+    if failed > 0 {
+        println!("{} strings kept in the source language", failed);
+    }
+    println!("{}{}", "Saved to ".green(), output.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_message() {
+        let pieces = split_message("Hello {name}, you have {{count}} new %s");
+        assert_eq!(
+            protect(&pieces),
+            "Hello {0}, you have {1} new {2}".to_string()
+        );
+        let pieces = split_message("{count, plural, one {# item} other {# items}} left");
+        assert_eq!(protect(&pieces), "{0} item{1} items{2} left".to_string());
+        let restored = restore("{0} 个项目{1} 个项目{2}剩余", &pieces).unwrap();
+        assert_eq!(
+            restored,
+            "{count, plural, one {# 个项目} other {# 个项目}}剩余"
+        );
+        assert!(restore("{0} 个项目 剩余", &pieces).is_none());
+        let pieces = split_message("{1} and {0}");
+        assert_eq!(restore("{0} 和 {1}", &pieces).unwrap(), "{1} 和 {0}");
+        assert!(!need_translate(&split_message("{count} %d")));
+    }
+
+    #[test]
+    fn test_split_markup() {
+        let html = "<p>Click <a href=\"/x\">here</a> &amp; {more}</p><!-- <b> -->";
+        let pieces = split_markup(html);
+        assert_eq!(protect(&pieces), "{0}Click {1}here{2} {3} {4}more{5}");
+        assert_eq!(restore(&protect(&pieces), &pieces).unwrap(), html);
+        // the translator may move the tags around
+        assert_eq!(
+            restore(
+                "{0}点击{1}这里{2}",
+                &split_markup("<p>Click <b>here</b></p>")
+            )
+            .unwrap(),
+            "<p>点击<b>这里</b></p>"
+        );
+        // an unclosed tag is text
+        assert_eq!(protect(&split_markup("a < b")), "a < b");
+    }
+
+    #[test]
+    fn test_incremental_leaves() {
+        let source: Value =
+            serde_json::from_str(r#"{"b": {"title": "Title", "count": 3}, "a": ["One", "Two"]}"#)
+                .unwrap();
+        let target: Value =
+            serde_json::from_str(r#"{"b": {"title": "标题"}, "a": ["一"]}"#).unwrap();
+        let mut leaves = Vec::new();
+        collect_leaves(&source, Some(&target), &mut Vec::new(), &mut leaves);
+        assert_eq!(leaves, vec![vec!["a".to_string(), "1".to_string()]]);
+
+        let mut value = source.clone();
+        merge_existing(&mut value, &target);
+        assert_eq!(
+            serde_json::to_string(&value).unwrap(),
+            r#"{"b":{"title":"标题","count":3},"a":["一","Two"]}"#
+        );
+    }
+}
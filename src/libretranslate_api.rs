@@ -0,0 +1,275 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::Duration;
+
+use crate::errors::LibreTranslateError;
+use crate::utils::{build_client, SelectText};
+use crate::TranslateResult;
+use crate::TIMEOUT;
+
+/// Default address of a self-hosted libretranslate instance
+const LIBRETRANSLATE_URL: &str = "http://127.0.0.1:5000";
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DetectedLanguage {
+    pub confidence: f32,
+    pub language: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LibreTranslateResponse {
+    #[serde(rename = "translatedText")]
+    pub translated_text: serde_json::Value,
+    #[serde(default)]
+    pub alternatives: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LibreTranslateLanguage {
+    pub code: String,
+    pub name: String,
+    #[serde(default)]
+    pub targets: Vec<String>,
+}
+
+fn build_url(endpoint: &str, path: &str) -> String {
+    let endpoint = match endpoint {
+        "null" => LIBRETRANSLATE_URL,
+        _ => endpoint.trim_end_matches('/'),
+    };
+    format!("{}{}", endpoint, path)
+}
+
+/// Attach the api key to the json body, the key is optional for the self-hosted instance
+fn with_key(mut body: serde_json::Value, auth_key: &str) -> serde_json::Value {
+    if auth_key != "null" && !auth_key.is_empty() {
+        body["api_key"] = json!(auth_key);
+    }
+    body
+}
+
+/// The error body is `{"error": "..."}` for all the failed requests
+async fn check_response(res: reqwest::Response) -> Result<reqwest::Response> {
+    if res.status().is_success() {
+        return Ok(res);
+    }
+    let status = res.status().as_u16();
+    let message = match res.json::<serde_json::Value>().await {
+        Ok(v) => v["error"].as_str().unwrap_or("unknown error").to_string(),
+        Err(_) => "unknown error".to_string(),
+    };
+    Err(LibreTranslateError(format!("{} ({})", message, status)).into())
+}
+
+pub async fn detect_language(
+    content: &str,
+    proxy_str: &str,
+    auth_key: &str,
+    endpoint: &str,
+) -> Result<String> {
+    let client = build_client(proxy_str);
+    let res = client
+        .post(build_url(endpoint, "/detect"))
+        .json(&with_key(json!({ "q": content }), auth_key))
+        .timeout(Duration::from_secs(TIMEOUT))
+        .send()
+        .await?;
+    let detected = check_response(res)
+        .await?
+        .json::<Vec<DetectedLanguage>>()
+        .await?;
+    match detected.into_iter().next() {
+        Some(d) => Ok(d.language),
+        None => Err(LibreTranslateError("no language detected".to_string()).into()),
+    }
+}
+
+pub async fn languages(
+    proxy_str: &str,
+    auth_key: &str,
+    endpoint: &str,
+) -> Result<Vec<LibreTranslateLanguage>> {
+    let client = build_client(proxy_str);
+    let mut request = client.get(build_url(endpoint, "/languages"));
+    if auth_key != "null" && !auth_key.is_empty() {
+        request = request.query(&[("api_key", auth_key)]);
+    }
+    let res = request.timeout(Duration::from_secs(TIMEOUT)).send().await?;
+    Ok(check_response(res)
+        .await?
+        .json::<Vec<LibreTranslateLanguage>>()
+        .await?)
+}
+
+pub async fn translate_libretranslate(
+    sl: &str, // source language
+    tl: &str, // target language
+    content: &str,
+    proxy_str: &str,
+    auth_key: &str,
+    endpoint: &str,
+) -> Result<Vec<TranslateResult>> {
+    let content = SelectText::unescape(content);
+    let sl = match sl {
+        "auto" => detect_language(&content, proxy_str, auth_key, endpoint).await?,
+        _ => sl.to_string(),
+    };
+    // ask for the alternatives of a single word, the old servers ignore it
+    let alternatives = match content.contains(' ') {
+        true => 0,
+        false => 3,
+    };
+    let body = json!({
+        "q": content,
+        "source": sl,
+        "target": tl,
+        "format": "text",
+        "alternatives": alternatives,
+    });
+
+    let client = build_client(proxy_str);
+    let res = client
+        .post(build_url(endpoint, "/translate"))
+        .json(&with_key(body, auth_key))
+        .timeout(Duration::from_secs(TIMEOUT))
+        .send()
+        .await?;
+    let res = check_response(res)
+        .await?
+        .json::<LibreTranslateResponse>()
+        .await?;
+
+    let item = TranslateResult {
+        orig: content.to_string(),
+        trans: res.translated_text.as_str().unwrap_or("").to_string(),
+        alter: res.alternatives,
+        dict: None,
+        romanization: None,
+        correction: None,
+        detected_lang: None,
+    };
+    Ok(vec![item])
+}
+
+pub async fn translate_batch_libretranslate(
+    sl: &str, // source language
+    tl: &str, // target language
+    texts: &[String],
+    proxy_str: &str,
+    auth_key: &str,
+    endpoint: &str,
+) -> Result<Vec<String>> {
+    let body = json!({
+        "q": texts,
+        "source": sl,
+        "target": tl,
+        "format": "text",
+    });
+    let client = build_client(proxy_str);
+    let res = client
+        .post(build_url(endpoint, "/translate"))
+        .json(&with_key(body, auth_key))
+        .timeout(Duration::from_secs(TIMEOUT))
+        .send()
+        .await?;
+    let res = check_response(res)
+        .await?
+        .json::<LibreTranslateResponse>()
+        .await?;
+    // a list of texts is translated into a list
+    match res.translated_text.as_array() {
+        Some(a) => Ok(a
+            .iter()
+            .map(|t| t.as_str().unwrap_or("").to_string())
+            .collect()),
+        None => Err(LibreTranslateError("the batch translation is not a list".to_string()).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::HttpResponse;
+    use crate::test_support::mock_server;
+
+    async fn libretranslate_server() -> String {
+        mock_server(|request| {
+            let body: serde_json::Value =
+                serde_json::from_slice(&request.body).unwrap_or_default();
+            if body.get("api_key").is_some() && body["api_key"] != "secret" {
+                return HttpResponse::json(403, json!({ "error": "Invalid API key" }));
+            }
+            match request.path() {
+                "/detect" => HttpResponse::json(200, json!([{ "confidence": 90.0, "language": "fr" }])),
+                "/languages" => HttpResponse::json(
+                    200,
+                    json!([{ "code": "en", "name": "English", "targets": ["ja", "zh"] }]),
+                ),
+                "/translate" => match &body["q"] {
+                    // an old server which does not translate lists
+                    serde_json::Value::Array(q) if q[0] == "old" => {
+                        HttpResponse::json(200, json!({ "translatedText": "old" }))
+                    }
+                    serde_json::Value::Array(q) => {
+                        let trans: Vec<String> = q.iter().map(|t| format!("[{}]", t.as_str().unwrap())).collect();
+                        HttpResponse::json(200, json!({ "translatedText": trans }))
+                    }
+                    q => HttpResponse::json(
+                        200,
+                        json!({
+                            "translatedText": format!("{}:{}", body["source"].as_str().unwrap(), q.as_str().unwrap()),
+                            "alternatives": ["alt"],
+                        }),
+                    ),
+                },
+                _ => HttpResponse::json(404, json!({ "error": "Not Found" })),
+            }
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_translate() {
+        let url = libretranslate_server().await;
+        let r = translate_libretranslate("en", "ja", "word", "null", "null", &url)
+            .await
+            .unwrap();
+        assert_eq!(r[0].trans, "en:word");
+        assert_eq!(r[0].alter, vec!["alt"]);
+
+        // the source language is detected first
+        let r = translate_libretranslate("auto", "ja", "a%26b", "null", "secret", &url)
+            .await
+            .unwrap();
+        assert_eq!(r[0].trans, "fr:a&b");
+
+        let texts = vec!["a".to_string(), "b".to_string()];
+        let r = translate_batch_libretranslate("en", "ja", &texts, "null", "null", &url)
+            .await
+            .unwrap();
+        assert_eq!(r, vec!["[a]", "[b]"]);
+
+        let texts = vec!["old".to_string(), "b".to_string()];
+        let e = translate_batch_libretranslate("en", "ja", &texts, "null", "null", &url)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "libretranslate error: the batch translation is not a list"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_languages_and_error() {
+        let url = libretranslate_server().await;
+        let langs = languages("null", "null", &url).await.unwrap();
+        assert_eq!(langs[0].code, "en");
+        assert_eq!(langs[0].targets, vec!["ja", "zh"]);
+
+        let e = translate_libretranslate("en", "ja", "word", "null", "wrong", &url)
+            .await
+            .unwrap_err();
+        assert_eq!(e.to_string(), "libretranslate error: Invalid API key (403)");
+    }
+}
@@ -0,0 +1,446 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::errors::LlmError;
+use crate::utils::{build_client, supported_langs, SelectText};
+use crate::TranslateResult;
+use crate::TIMEOUT;
+
+/// Default address of the openai compatible api of ollama
+const LLM_URL: &str = "http://127.0.0.1:11434/v1";
+const LLM_MODEL: &str = "llama3";
+/// `{sl}`, `{tl}` and `{domain}` are replaced before sending
+pub const LLM_PROMPT: &str = "You are a professional translator in the domain of {domain}. Translate the text from the user from {sl} to {tl}. Reply with the translation only, without any explanation or notes.";
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ChatChoice {
+    pub message: ChatMessage,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ChatResponse {
+    pub choices: Vec<ChatChoice>,
+}
+
+/// The options of the llm provider
+#[derive(Debug, Clone)]
+pub struct LlmOptions<'a> {
+    pub endpoint: &'a str,
+    pub model: &'a str,
+    pub prompt: &'a str,
+    pub domain: &'a str,
+    pub temperature: f32,
+}
+
+/// The model understands the language name better than the code
+fn lang_name(code: &str) -> &str {
+    match supported_langs("llm") {
+        Ok((langs, _)) => match langs.iter().find(|(_, c)| *c == code) {
+            Some((name, _)) => name,
+            None => code,
+        },
+        Err(_) => code,
+    }
+}
+
+pub fn render_prompt(sl: &str, tl: &str, options: &LlmOptions) -> String {
+    let prompt = match options.prompt {
+        "null" => LLM_PROMPT,
+        _ => options.prompt,
+    };
+    let domain = match options.domain {
+        "null" => "general",
+        _ => options.domain,
+    };
+    prompt
+        .replace("{sl}", lang_name(sl))
+        .replace("{tl}", lang_name(tl))
+        .replace("{domain}", domain)
+}
+
+fn build_request(
+    sl: &str,
+    tl: &str,
+    content: &str,
+    proxy_str: &str,
+    auth_key: &str,
+    options: &LlmOptions<'_>,
+    stream: bool,
+) -> reqwest::RequestBuilder {
+    let endpoint = match options.endpoint {
+        "null" => LLM_URL,
+        _ => options.endpoint.trim_end_matches('/'),
+    };
+    let model = match options.model {
+        "null" => LLM_MODEL,
+        _ => options.model,
+    };
+    let body = json!({
+        "model": model,
+        "temperature": options.temperature,
+        "stream": stream,
+        "messages": [
+            { "role": "system", "content": render_prompt(sl, tl, options) },
+            { "role": "user", "content": content },
+        ],
+    });
+
+    let client = build_client(proxy_str);
+    let mut request = client.post(format!("{}/chat/completions", endpoint));
+    // the local servers do not need a key
+    if auth_key != "null" && !auth_key.is_empty() {
+        request = request.bearer_auth(auth_key);
+    }
+    let request = request.json(&body);
+    // a stream may take longer than the timeout, it is only kept from going idle
+    match stream {
+        true => request,
+        false => request.timeout(Duration::from_secs(TIMEOUT)),
+    }
+}
+
+async fn check_response(res: reqwest::Response) -> Result<reqwest::Response> {
+    if res.status().is_success() {
+        return Ok(res);
+    }
+    let status = res.status().as_u16();
+    let message = match res.json::<serde_json::Value>().await {
+        Ok(v) => v["error"]["message"]
+            .as_str()
+            .or(v["error"].as_str())
+            .unwrap_or("unknown error")
+            .to_string(),
+        Err(_) => "unknown error".to_string(),
+    };
+    Err(LlmError(format!("{} ({})", message, status)).into())
+}
+
+async fn chat(
+    sl: &str,
+    tl: &str,
+    content: &str,
+    proxy_str: &str,
+    auth_key: &str,
+    options: &LlmOptions<'_>,
+) -> Result<String> {
+    let res = build_request(sl, tl, content, proxy_str, auth_key, options, false)
+        .send()
+        .await?;
+    let res = check_response(res).await?.json::<ChatResponse>().await?;
+    match res.choices.into_iter().next() {
+        Some(c) => Ok(c.message.content.trim().to_string()),
+        None => Err(LlmError("empty choices".to_string()).into()),
+    }
+}
+
+/// Split the chunks of a server-sent-event (`data: {...}`) or a
+/// newline delimited json response into the payloads
+#[derive(Debug, Default)]
+pub struct StreamParser {
+    buf: Vec<u8>,
+}
+
+impl StreamParser {
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buf.extend_from_slice(chunk);
+        let mut payloads = Vec::new();
+        // a chunk may end in the middle of a line (or a utf-8 char)
+        while let Some(pos) = self.buf.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buf.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line).trim().to_string();
+            let payload = match line.strip_prefix("data:") {
+                Some(p) => p.trim().to_string(),
+                None if line.starts_with('{') => line,
+                // blank lines, comments and the event fields of sse
+                None => continue,
+            };
+            if !payload.is_empty() {
+                payloads.push(payload);
+            }
+        }
+        payloads
+    }
+}
+
+/// The text of one streamed chunk, openai puts it in `delta`, ollama in `message`
+fn delta_content(payload: &serde_json::Value) -> &str {
+    let choice = &payload["choices"][0];
+    choice["delta"]["content"]
+        .as_str()
+        .or(payload["message"]["content"].as_str())
+        .unwrap_or("")
+}
+
+async fn chat_stream(
+    sl: &str,
+    tl: &str,
+    content: &str,
+    proxy_str: &str,
+    auth_key: &str,
+    options: &LlmOptions<'_>,
+    partial: &UnboundedSender<String>,
+) -> Result<String> {
+    let idle = Duration::from_secs(TIMEOUT);
+    let idle_error = || LlmError(format!("no data for {} seconds", TIMEOUT));
+    let request = build_request(sl, tl, content, proxy_str, auth_key, options, true).send();
+    let res = match tokio::time::timeout(idle, request).await {
+        Ok(res) => res?,
+        Err(_) => return Err(idle_error().into()),
+    };
+    let mut res = check_response(res).await?;
+
+    let mut parser = StreamParser::default();
+    let mut trans = String::new();
+    loop {
+        let chunk = match tokio::time::timeout(idle, res.chunk()).await {
+            Ok(chunk) => chunk?,
+            Err(_) => return Err(idle_error().into()),
+        };
+        let chunk = match chunk {
+            Some(chunk) => chunk,
+            None => break,
+        };
+        for payload in parser.push(&chunk) {
+            // the server may keep the connection open after the end
+            if payload == "[DONE]" {
+                return Ok(trans.trim().to_string());
+            }
+            let payload: serde_json::Value = serde_json::from_str(&payload)?;
+            trans.push_str(delta_content(&payload));
+            // the receiver may be gone, the translation goes on anyway
+            let _ = partial.send(trans.clone());
+        }
+    }
+    Ok(trans.trim().to_string())
+}
+
+pub async fn translate_llm(
+    sl: &str, // source language
+    tl: &str, // target language
+    content: &str,
+    proxy_str: &str,
+    auth_key: &str,
+    options: &LlmOptions<'_>,
+) -> Result<Vec<TranslateResult>> {
+    let content = SelectText::unescape(content);
+    let trans = chat(sl, tl, &content, proxy_str, auth_key, options).await?;
+    let item = TranslateResult {
+        orig: content,
+        trans,
+        alter: Vec::new(),
+        dict: None,
+        romanization: None,
+        correction: None,
+        detected_lang: None,
+    };
+    Ok(vec![item])
+}
+
+/// Same as `translate_llm`, the partial translation is sent while the tokens arrive
+pub async fn translate_llm_stream(
+    sl: &str, // source language
+    tl: &str, // target language
+    content: &str,
+    proxy_str: &str,
+    auth_key: &str,
+    options: &LlmOptions<'_>,
+    partial: &UnboundedSender<String>,
+) -> Result<Vec<TranslateResult>> {
+    let content = SelectText::unescape(content);
+    let trans = chat_stream(sl, tl, &content, proxy_str, auth_key, options, partial).await?;
+    let item = TranslateResult {
+        orig: content,
+        trans,
+        alter: Vec::new(),
+        dict: None,
+        romanization: None,
+        correction: None,
+        detected_lang: None,
+    };
+    Ok(vec![item])
+}
+
+pub async fn translate_batch_llm(
+    sl: &str, // source language
+    tl: &str, // target language
+    texts: &[String],
+    proxy_str: &str,
+    auth_key: &str,
+    options: &LlmOptions<'_>,
+) -> Result<Vec<String>> {
+    // one text per request, the model may merge or drop the lines of a joined text
+    let mut result_vec = Vec::new();
+    for t in texts {
+        result_vec.push(chat(sl, tl, t, proxy_str, auth_key, options).await?);
+    }
+    Ok(result_vec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::HttpResponse;
+    use crate::test_support::mock_server;
+
+    #[tokio::test]
+    async fn test_translate_llm() {
+        let url = mock_server(|request| {
+            let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            if request.path() != "/v1/chat/completions" {
+                return HttpResponse::json(404, json!({ "error": "not found" }));
+            }
+            if request.header("Authorization") != Some("Bearer sk-test") {
+                return HttpResponse::json(401, json!({ "error": { "message": "invalid key" } }));
+            }
+            // echo the system prompt and the model back
+            let reply = format!(
+                "{}|{}|{}",
+                body["model"].as_str().unwrap(),
+                body["messages"][0]["content"].as_str().unwrap(),
+                body["messages"][1]["content"].as_str().unwrap(),
+            );
+            HttpResponse::json(
+                200,
+                json!({ "choices": [{ "message": { "role": "assistant", "content": reply } }] }),
+            )
+        })
+        .await;
+        let endpoint = format!("{}/v1", url);
+        let options = LlmOptions {
+            endpoint: &endpoint,
+            model: "qwen2",
+            prompt: "{sl} to {tl} ({domain})",
+            domain: "academic computer science",
+            temperature: 0.2,
+        };
+        let r = translate_llm("en", "ja", "a%26b", "null", "sk-test", &options)
+            .await
+            .unwrap();
+        assert_eq!(
+            r[0].trans,
+            "qwen2|English to Japanese (academic computer science)|a&b"
+        );
+
+        let e = translate_llm("en", "ja", "text", "null", "null", &options)
+            .await
+            .unwrap_err();
+        assert_eq!(e.to_string(), "llm error: invalid key (401)");
+    }
+
+    #[test]
+    fn test_stream_parser() {
+        let mut parser = StreamParser::default();
+        assert!(parser.push(b"data: {\"a\"").is_empty());
+        assert_eq!(
+            parser.push(b":1}\n\n: comment\ndata: [DONE]\n"),
+            vec!["{\"a\":1}", "[DONE]"]
+        );
+        // ndjson and a utf-8 char split between two chunks
+        let text = "{\"message\":{\"content\":\"翻\"}}\n".as_bytes();
+        assert!(parser.push(&text[..24]).is_empty());
+        let payloads = parser.push(&text[24..]);
+        let payload: serde_json::Value = serde_json::from_str(&payloads[0]).unwrap();
+        assert_eq!(delta_content(&payload), "翻");
+    }
+
+    #[tokio::test]
+    async fn test_translate_llm_stream() {
+        let url = mock_server(|request| {
+            let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            assert_eq!(body["stream"], true);
+            let mut sse = String::new();
+            for delta in ["こん", "にち", "は"] {
+                let chunk = json!({ "choices": [{ "delta": { "content": delta } }] });
+                sse.push_str(&format!("data: {}\n\n", chunk));
+            }
+            sse.push_str("data: [DONE]\n\n");
+            HttpResponse {
+                status: 200,
+                content_type: "text/event-stream",
+                body: sse.into_bytes(),
+            }
+        })
+        .await;
+        let endpoint = format!("{}/v1", url);
+        let options = LlmOptions {
+            endpoint: &endpoint,
+            model: "null",
+            prompt: "null",
+            domain: "null",
+            temperature: 0.2,
+        };
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let r = translate_llm_stream("en", "ja", "hello", "null", "null", &options, &tx)
+            .await
+            .unwrap();
+        assert_eq!(r[0].trans, "こんにちは");
+        drop(tx);
+        let mut partials = Vec::new();
+        while let Some(p) = rx.recv().await {
+            partials.push(p);
+        }
+        assert_eq!(partials, vec!["こん", "こんにち", "こんにちは"]);
+    }
+
+    #[tokio::test]
+    async fn test_stream_done() {
+        use tokio::io::AsyncWriteExt;
+
+        // the server keeps the connection open after [DONE]
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            crate::server::read_request(&mut stream, usize::MAX)
+                .await
+                .unwrap();
+            let chunk = json!({ "choices": [{ "delta": { "content": "はい" } }] });
+            let sse = format!("data: {}\n\ndata: [DONE]\n\n", chunk);
+            let head = "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ntransfer-encoding: chunked\r\n\r\n";
+            let body = format!("{:x}\r\n{}\r\n", sse.len(), sse);
+            stream.write_all(head.as_bytes()).await.unwrap();
+            stream.write_all(body.as_bytes()).await.unwrap();
+            tokio::time::sleep(Duration::from_secs(TIMEOUT)).await;
+        });
+        let endpoint = format!("http://{}/v1", addr);
+        let options = LlmOptions {
+            endpoint: &endpoint,
+            model: "null",
+            prompt: "null",
+            domain: "null",
+            temperature: 0.2,
+        };
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let r = tokio::time::timeout(
+            Duration::from_secs(5),
+            translate_llm_stream("en", "ja", "yes", "null", "null", &options, &tx),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(r[0].trans, "はい");
+    }
+
+    #[test]
+    fn test_default_prompt() {
+        let options = LlmOptions {
+            endpoint: "null",
+            model: "null",
+            prompt: "null",
+            domain: "null",
+            temperature: 0.2,
+        };
+        let prompt = render_prompt("en", "zh-CN", &options);
+        assert!(prompt.contains("domain of general"));
+        assert!(prompt.contains("from English to Chinese (Simplified)"));
+    }
+}
@@ -1,42 +1,112 @@
 use anyhow::Result;
 use chrono::Local;
 use clap::Parser;
+use clap::Subcommand;
 use colored::Colorize;
+use serde::Serialize;
+use std::io::Write;
+use std::path::Path;
 use std::thread;
 use std::time::Duration;
 use std::time::SystemTime;
+use tokio::sync::mpsc::unbounded_channel;
+use tokio::sync::mpsc::UnboundedSender;
 
+mod aws_api;
+mod azure_api;
+mod baidu_api;
 mod deepl_api;
+mod deepl_document;
+mod deepl_glossary;
+mod deepl_languages;
 mod errors;
+mod exec_api;
 mod google_api;
+mod googlecloud_api;
+mod i18n;
+mod libretranslate_api;
+mod llm_api;
+mod native;
+mod offline_api;
+mod po;
+mod server;
+mod subtitle;
+#[cfg(test)]
+mod test_support;
 mod utils;
+mod youdao_api;
 
-use deepl_api::translate_free;
-use deepl_api::translate_pro;
+use aws_api::translate_aws;
+use aws_api::translate_batch_aws;
+use aws_api::AwsOptions;
+use azure_api::translate_azure;
+use azure_api::translate_batch_azure;
+use baidu_api::translate_baidu;
+use baidu_api::translate_batch_baidu;
+use deepl_api::translate_batch_deepl;
+use deepl_api::translate_deepl;
+use deepl_api::usage_command;
+use deepl_api::DeepLOptions;
+use deepl_document::translate_document_file;
+use deepl_glossary::glossary_command;
+use deepl_languages::standardized_deepl_lang;
+use errors::BatchLengthError;
+use errors::ProfileNotFoundError;
 use errors::UnsupportApiError;
 use errors::UnsupportOsError;
+use exec_api::translate_batch_exec;
+use exec_api::translate_exec;
+use exec_api::ExecOptions;
+use google_api::translate_batch;
 use google_api::translate_longstring;
+use google_api::translate_markup;
 use google_api::translate_shortword;
+use googlecloud_api::translate_batch_googlecloud;
+use googlecloud_api::translate_googlecloud;
+use i18n::translate_resource_file;
+use libretranslate_api::translate_batch_libretranslate;
+use libretranslate_api::translate_libretranslate;
+use llm_api::translate_batch_llm;
+use llm_api::translate_llm;
+use llm_api::translate_llm_stream;
+use llm_api::LlmOptions;
+use native::browser_launch_args;
+use native::is_browser_launch;
+use native::native_args_path;
+use native::native_host;
+use native::native_manifest;
+use offline_api::translate_batch_offline;
+use offline_api::translate_offline;
+use offline_api::OfflineOptions;
+use po::translate_po_file;
+use server::serve;
+use subtitle::translate_subtitle_file;
+use utils::config_dir;
+use utils::read_args_file;
 use utils::standardized_lang;
 use utils::SelectText;
+use youdao_api::translate_batch_youdao;
+use youdao_api::translate_youdao;
 
 const TIMEOUT: u64 = 60;
 
 /// Simple program to translate text
-#[derive(Parser, Debug)]
-#[clap(author, version, about, long_about = None)]
+#[derive(Parser, Debug, Clone)]
+#[clap(author, version, about, long_about = None, args_override_self = true)]
 struct Args {
+    #[clap(subcommand)]
+    command: Option<Commands>,
     /// Source language
-    #[clap(short, long, default_value = "English")]
+    #[clap(short, long, global = true, default_value = "English")]
     sl: String,
     /// Target translation language
-    #[clap(short, long, default_value = "Chinese (Simplified)")]
+    #[clap(short, long, global = true, default_value = "Chinese (Simplified)")]
     tl: String,
     /// Fast mode or slow mode
     #[clap(short, long, default_value_t = 1.0)]
     fast_mode: f32,
     /// Proxy set (socks5://192.168.1.1:9000)
-    #[clap(short, long, default_value = "null")]
+    #[clap(short, long, global = true, default_value = "null")]
     proxy: String,
     /// Translate new text and clear the screen
     #[clap(short, long, default_value_t = 0)]
@@ -47,18 +117,471 @@ struct Args {
     /// Auto break the sentence or not
     #[clap(long, action)]
     disable_auto_break: bool,
+    /// Show the latin transliteration of the text (google)
+    #[clap(long, action)]
+    romanize: bool,
+    /// Translate the corrected spelling instead when google is sure about it
+    #[clap(long, action)]
+    auto_correct: bool,
     /// Linux get text from clipboard
     #[clap(long, action)]
     use_clipboard: bool,
     /// Specify translation API provider
-    #[clap(short, long, default_value = "google")]
+    #[clap(short, long, global = true, default_value = "google")]
     api: String,
     /// API auth key
-    #[clap(long, default_value = "null")]
+    #[clap(long, global = true, default_value = "null")]
     auth_key: String,
+    /// API app id, the secret goes to --auth-key (baidu, youdao)
+    #[clap(long, global = true, default_value = "null")]
+    app_id: String,
+    /// API endpoint (libretranslate: http://127.0.0.1:5000, llm: http://127.0.0.1:11434/v1)
+    #[clap(long, global = true, default_value = "null")]
+    endpoint: String,
+    /// Format of the text (text, html or xml), the tags are kept in the translation
+    #[clap(long, global = true, default_value = "text", value_parser = ["text", "html", "xml"])]
+    input_format: String,
+    /// Custom terminology names, comma separated (aws)
+    #[clap(long, global = true, default_value = "null")]
+    terminology: String,
+    /// Formality of the translation (aws: formal or informal, deepl: more, less, prefer_more
+    /// or prefer_less)
+    #[clap(long, global = true, default_value = "null")]
+    formality: String,
+    /// API region (azure: westeurope, aws: us-east-1)
+    #[clap(long, global = true, default_value = "null")]
+    region: String,
+    /// LLM model name (default: llama3)
+    #[clap(long, global = true, default_value = "null")]
+    llm_model: String,
+    /// LLM system prompt template, {sl}, {tl} and {domain} are replaced
+    #[clap(long, global = true, default_value = "null")]
+    llm_prompt: String,
+    /// LLM domain hint (academic computer science)
+    #[clap(long, global = true, default_value = "null")]
+    llm_domain: String,
+    /// LLM sampling temperature
+    #[clap(long, global = true, default_value_t = 0.2)]
+    llm_temperature: f32,
+    /// Command of the exec provider, it exchanges json lines over stdin and stdout
+    #[clap(long, global = true, default_value = "null")]
+    exec_command: String,
+    /// Keep the exec command running and send all the requests to it
+    #[clap(long, global = true, action)]
+    exec_persistent: bool,
+    /// Option passed to the exec command (key=value), can be repeated
+    #[clap(long, global = true)]
+    exec_option: Vec<String>,
+    /// Delay of the echo and pseudo providers in milliseconds
+    #[clap(long, global = true, default_value_t = 0)]
+    offline_latency: u64,
+    /// Make every n-th request of the echo and pseudo providers fail (0: never)
+    #[clap(long, global = true, default_value_t = 0)]
+    offline_fail_every: usize,
+    /// DeepL glossary id or name, a name is matched with the language pair
+    #[clap(long, global = true, default_value = "null")]
+    glossary: String,
+    /// Own DeepL character limit of the billing period, checked before sending (0: off)
+    #[clap(long, global = true, default_value_t = 0)]
+    deepl_budget: u64,
+    /// Check the DeepL usage before sending and refuse a text over the remaining characters
+    #[clap(long, global = true, action)]
+    deepl_preflight: bool,
+    /// Warn when the DeepL usage reaches this percent of the limit (0: off), without
+    /// --deepl-preflight or --deepl-budget the usage is checked every 10 minutes at most
+    #[clap(long, global = true, default_value_t = 0)]
+    deepl_warn_percent: u64,
+    /// Text around the selection which helps the DeepL translation, it is not translated
+    #[clap(long, global = true, default_value = "null")]
+    deepl_context: String,
+    /// How DeepL splits the text into sentences (0, 1 or nonewlines)
+    #[clap(long, global = true, default_value = "1")]
+    deepl_split_sentences: String,
+    /// Keep the punctuation and the casing of the DeepL input
+    #[clap(long, global = true, action)]
+    deepl_preserve_formatting: bool,
+    /// DeepL model type (latency_optimized, quality_optimized or prefer_quality_optimized)
+    #[clap(long, global = true, default_value = "null")]
+    deepl_model: String,
+    /// Comma separated tags whose content DeepL does not translate (html or xml input)
+    #[clap(long, global = true, default_value = "null")]
+    deepl_ignore_tags: String,
+    /// Read the options of ~/.config/translator-rs/profiles/<name>.args, one per line,
+    /// the command line options win
+    #[clap(long, global = true, default_value = "null")]
+    profile: String,
     /// Theme (light or dark)
     #[clap(long, default_value = "light")]
     theme: String,
+    /// Show the partial translation while it is streaming (llm)
+    #[clap(long, action)]
+    stream: bool,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum Commands {
+    /// Translate a SRT or WebVTT subtitle file and keep the cue timings
+    Subtitle {
+        /// Subtitle file (.srt or .vtt)
+        input: String,
+        /// Output file (default: <input>.<target language>.<ext>)
+        #[clap(short, long, default_value = "null")]
+        output: String,
+        /// Merge the cues which split one sentence before translating
+        #[clap(long, action)]
+        merge_sentences: bool,
+    },
+    /// Fill in the empty msgstr entries of a gettext .po catalog
+    Po {
+        /// Catalog file (.po)
+        input: String,
+        /// Output file (default: update the input file)
+        #[clap(short, long, default_value = "null")]
+        output: String,
+    },
+    /// Translate the string values of a JSON, YAML or TOML resource file
+    I18n {
+        /// Resource file (.json, .yaml or .toml)
+        input: String,
+        /// Output file (default: en.json => <target language>.json)
+        #[clap(short, long, default_value = "null")]
+        output: String,
+        /// Only translate the keys missing from the existing output file
+        #[clap(long, action)]
+        incremental: bool,
+    },
+    /// Run a local http server which exposes the translation api
+    Serve {
+        /// Address to listen on
+        #[clap(long, default_value = "127.0.0.1:8787")]
+        bind: String,
+        /// Require the `Authorization: Bearer <token>` header, needed for a non-loopback address
+        #[clap(long, default_value = "null")]
+        token: String,
+        /// Max size of the request body in bytes
+        #[clap(long, default_value_t = 65536)]
+        max_body: usize,
+        /// Origin of a web page which may call the server (https://example.com or *), can be
+        /// repeated
+        #[clap(long)]
+        allow_origin: Vec<String>,
+    },
+    /// Run as the native messaging host of a browser extension
+    NativeHost {
+        /// Arguments appended by the browser
+        #[clap(hide = true, allow_hyphen_values = true, trailing_var_arg = true)]
+        browser_args: Vec<String>,
+    },
+    /// Print the native messaging host manifest
+    NativeManifest {
+        /// Browser (chrome, chromium, edge or firefox)
+        #[clap(long, default_value = "chrome")]
+        browser: String,
+        /// Extension id which is allowed to connect to the host
+        #[clap(long)]
+        extension_id: String,
+        /// Path of the host executable or the wrapper script (default: this program)
+        #[clap(long, default_value = "null")]
+        path: String,
+    },
+    /// Translate a .docx, .pptx, .pdf or other document with DeepL and keep the layout
+    DeeplDoc {
+        /// Document file
+        input: String,
+        /// Output file (default: <input>.<target language>.<ext>)
+        #[clap(short, long, default_value = "null")]
+        output: String,
+        /// Seconds between the status requests
+        #[clap(long, default_value_t = 5)]
+        interval: u64,
+    },
+    /// Show the DeepL character usage of the billing period
+    Usage,
+    /// Manage the DeepL glossaries, the languages come from -s and -t
+    Glossary {
+        #[clap(subcommand)]
+        action: GlossaryAction,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum GlossaryAction {
+    /// Upload a TSV or CSV term list as a new glossary
+    Create {
+        /// Glossary name
+        name: String,
+        /// Term list (.tsv or .csv), one `source<TAB>target` per line
+        file: String,
+    },
+    /// List the glossaries
+    List,
+    /// Show the details of a glossary
+    Show {
+        /// Glossary id
+        id: String,
+        /// Print the entries too
+        #[clap(long, action)]
+        entries: bool,
+    },
+    /// Delete a glossary
+    Delete {
+        /// Glossary id
+        id: String,
+    },
+}
+
+impl Args {
+    fn deepl_options(&self) -> DeepLOptions<'_> {
+        DeepLOptions {
+            endpoint: &self.endpoint,
+            glossary: &self.glossary,
+            budget: self.deepl_budget,
+            preflight: self.deepl_preflight,
+            warn_percent: self.deepl_warn_percent,
+            formality: &self.formality,
+            context: &self.deepl_context,
+            split_sentences: &self.deepl_split_sentences,
+            preserve_formatting: self.deepl_preserve_formatting,
+            model_type: &self.deepl_model,
+            tag_handling: &self.input_format,
+            ignore_tags: &self.deepl_ignore_tags,
+        }
+    }
+    fn offline_options(&self) -> OfflineOptions {
+        OfflineOptions {
+            latency: self.offline_latency,
+            fail_every: self.offline_fail_every,
+        }
+    }
+    fn exec_options(&self) -> ExecOptions<'_> {
+        ExecOptions {
+            command: &self.exec_command,
+            persistent: self.exec_persistent,
+            options: &self.exec_option,
+        }
+    }
+    fn aws_options(&self) -> AwsOptions<'_> {
+        AwsOptions {
+            region: &self.region,
+            endpoint: &self.endpoint,
+            terminology: &self.terminology,
+            formality: &self.formality,
+        }
+    }
+    fn llm_options(&self) -> LlmOptions<'_> {
+        LlmOptions {
+            endpoint: &self.endpoint,
+            model: &self.llm_model,
+            prompt: &self.llm_prompt,
+            domain: &self.llm_domain,
+            temperature: self.llm_temperature,
+        }
+    }
+}
+
+/// Put the options of the profile before the command line ones, which win
+fn profile_args(argv: &[String], name: &str, config_dir: Option<&Path>) -> Result<Vec<String>> {
+    let path = match config_dir {
+        Some(dir) => dir.join("profiles").join(format!("{}.args", name)),
+        None => return Err(ProfileNotFoundError(name.to_string()).into()),
+    };
+    match read_args_file(&path) {
+        Some(options) => Ok(argv[..1]
+            .iter()
+            .cloned()
+            .chain(options)
+            .chain(argv[1..].iter().cloned())
+            .collect()),
+        None => Err(ProfileNotFoundError(path.display().to_string()).into()),
+    }
+}
+
+/// The servers check the languages of every request, the other subcommands have no languages
+fn needs_langs(command: &Option<Commands>) -> bool {
+    !matches!(
+        command,
+        Some(Commands::Serve { .. })
+            | Some(Commands::NativeHost { .. })
+            | Some(Commands::NativeManifest { .. })
+            | Some(Commands::Usage)
+            | Some(Commands::Glossary {
+                action: GlossaryAction::List
+                    | GlossaryAction::Show { .. }
+                    | GlossaryAction::Delete { .. }
+            })
+    )
+}
+
+/// The codes of the provider, the deepl languages are checked against its lists
+async fn resolve_langs(sl: &str, tl: &str, args: &Args) -> Result<(String, String)> {
+    match args.api.as_str() {
+        "deepl" | "deeplpro" => standardized_deepl_lang(sl, tl, args).await,
+        _ => {
+            let (sl, tl) = standardized_lang(sl, tl, &args.api)?;
+            Ok((sl.to_string(), tl.to_string()))
+        }
+    }
+}
+
+/// Call the translation api of the provider, the error is returned to the caller
+async fn translate_content(
+    sl: &str,
+    tl: &str,
+    content: &str,
+    args: &Args,
+) -> Result<Vec<TranslateResult>> {
+    let contains_symbol = |input_string: &str| -> bool { input_string.contains(' ') };
+    let proxy = args.proxy.as_str();
+    let auth_key = args.auth_key.as_str();
+    // let proxy = reqwest::Proxy::http("socks5://192.168.1.1:9000").expect("set proxy failed");
+
+    match args.api.as_str() {
+        "google" if args.input_format != "text" => translate_markup(sl, tl, content, proxy).await,
+        "google" => match contains_symbol(content) {
+            true => translate_longstring(sl, tl, content, proxy).await,
+            false => translate_shortword(sl, tl, content, proxy).await,
+        },
+        "googlecloud" => {
+            let format = &args.input_format;
+            translate_googlecloud(sl, tl, content, format, proxy, auth_key, &args.endpoint).await
+        }
+        "deepl" | "deeplpro" => {
+            translate_deepl(sl, tl, content, proxy, auth_key, &args.deepl_options()).await
+        }
+        "libretranslate" => {
+            translate_libretranslate(sl, tl, content, proxy, auth_key, &args.endpoint).await
+        }
+        "llm" => translate_llm(sl, tl, content, proxy, auth_key, &args.llm_options()).await,
+        "azure" => {
+            translate_azure(
+                sl,
+                tl,
+                content,
+                proxy,
+                auth_key,
+                &args.region,
+                &args.endpoint,
+            )
+            .await
+        }
+        "baidu" => {
+            translate_baidu(
+                sl,
+                tl,
+                content,
+                proxy,
+                &args.app_id,
+                auth_key,
+                &args.endpoint,
+            )
+            .await
+        }
+        "aws" => translate_aws(sl, tl, content, proxy, &args.aws_options()).await,
+        "exec" => translate_exec(sl, tl, content, &args.exec_options()).await,
+        "echo" | "pseudo" => translate_offline(&args.api, content, &args.offline_options()).await,
+        "youdao" => {
+            translate_youdao(
+                sl,
+                tl,
+                content,
+                proxy,
+                &args.app_id,
+                auth_key,
+                &args.endpoint,
+            )
+            .await
+        }
+        _ => Err(UnsupportApiError.into()),
+    }
+}
+
+/// Same as `translate_content`, the token-streaming providers send the partial translation
+async fn translate_content_stream(
+    sl: &str,
+    tl: &str,
+    content: &str,
+    args: &Args,
+    partial: &UnboundedSender<String>,
+) -> Result<Vec<TranslateResult>> {
+    let proxy = args.proxy.as_str();
+    let auth_key = args.auth_key.as_str();
+    match args.api.as_str() {
+        "llm" => {
+            translate_llm_stream(
+                sl,
+                tl,
+                content,
+                proxy,
+                auth_key,
+                &args.llm_options(),
+                partial,
+            )
+            .await
+        }
+        _ => translate_content(sl, tl, content, args).await,
+    }
+}
+
+/// Show the partial translation on a live `[T]` line until the provider finishes
+async fn translate_content_live(
+    sl: &str,
+    tl: &str,
+    content: &str,
+    args: &Args,
+) -> Result<Vec<TranslateResult>> {
+    let (tx, mut rx) = unbounded_channel();
+    let results = {
+        let translating = translate_content_stream(sl, tl, content, args, &tx);
+        tokio::pin!(translating);
+        loop {
+            tokio::select! {
+                r = &mut translating => break r,
+                Some(partial) = rx.recv() => TranslateResults::show_partial(&partial),
+            }
+        }
+    };
+    TranslateResults::clear_partial();
+    results
+}
+
+/// Translate the corrected spelling again, the first translation is kept when it fails
+async fn apply_correction(
+    sl: &str,
+    tl: &str,
+    results: Vec<TranslateResult>,
+    args: &Args,
+) -> Vec<TranslateResult> {
+    let text = match results.as_slice() {
+        [r] => match r.correction.as_ref().filter(|c| c.confident && !c.applied) {
+            Some(c) => c.text.clone(),
+            None => return results,
+        },
+        _ => return results,
+    };
+    match translate_content(sl, tl, &text, args).await {
+        Ok(corrected) => corrected_results(results, corrected),
+        Err(_) => results,
+    }
+}
+
+/// A valid word without a dictionary entry (`softmax`) gets suggestions as well,
+/// so the correction is only taken when the corrected word has an entry
+fn corrected_results(
+    results: Vec<TranslateResult>,
+    mut corrected: Vec<TranslateResult>,
+) -> Vec<TranslateResult> {
+    match (results.as_slice(), corrected.as_mut_slice()) {
+        ([r], [c]) if c.dict.is_some() => {
+            c.correction = r.correction.as_ref().map(|correction| Correction {
+                orig: correction.orig.clone(),
+                text: correction.text.clone(),
+                confident: true,
+                applied: true,
+            });
+            corrected
+        }
+        _ => results,
+    }
 }
 
 async fn translate<'a>(
@@ -66,54 +589,34 @@ async fn translate<'a>(
     tl: &'a str,
     content: &'a str,
     index: usize,
-    proxy: &'a str,
-    theme: &'a str,
-    api: &'a str,
-    auth_key: &'a str,
+    args: &'a Args,
 ) -> Result<TranslateResults<'a>> {
-    let contains_symbol = |input_string: &str| -> bool { input_string.contains(" ") };
     let start_time = SystemTime::now();
-    // let proxy = reqwest::Proxy::http("socks5://192.168.1.1:9000").expect("set proxy failed");
-
-    let results = match api {
-        "google" => match contains_symbol(content) {
-            true => match translate_longstring(sl, tl, content, proxy).await {
-                Ok(r) => r,
-                Err(e) => {
-                    println!("translate failed: {}", e);
-                    vec![]
-                }
-            },
-            false => match translate_shortword(sl, tl, content, proxy).await {
-                Ok(r) => r,
-                Err(e) => {
-                    println!("translate failed: {}", e);
-                    vec![]
-                }
-            },
-        },
-        "deepl" => match translate_free(sl, tl, content, proxy, auth_key).await {
-            Ok(t) => t,
-            Err(e) => {
-                println!("translate failed: {}", e);
-                vec![]
-            }
-        },
-        "deeplpro" => match translate_pro(sl, tl, content, proxy, auth_key).await {
-            Ok(t) => t,
-            Err(e) => {
+    let results = match args.stream {
+        true => translate_content_live(sl, tl, content, args).await,
+        false => translate_content(sl, tl, content, args).await,
+    };
+    let results = match results {
+        Ok(r) => r,
+        Err(e) => match e.downcast_ref::<UnsupportApiError>() {
+            Some(_) => return Err(e),
+            None => {
                 println!("translate failed: {}", e);
                 vec![]
             }
         },
-        _ => return Err(UnsupportApiError.into()),
+    };
+    let results = match args.auto_correct {
+        true => apply_correction(sl, tl, results, args).await,
+        false => results,
     };
     // println!("{:?}", result_vec);
     let end_time = SystemTime::now();
     let trets = TranslateResults {
         results,
-        proxy,
-        theme,
+        proxy: &args.proxy,
+        theme: &args.theme,
+        romanize: args.romanize,
         start_time,
         end_time,
         index,
@@ -121,24 +624,272 @@ async fn translate<'a>(
     Ok(trets)
 }
 
-#[derive(Debug)]
+/// Translate a list of independent texts, keeping the order and the length of the input
+async fn translate_texts(sl: &str, tl: &str, texts: &[String], args: &Args) -> Result<Vec<String>> {
+    let trans = translate_texts_api(sl, tl, texts, args).await?;
+    // a short result would leave the last cues or keys untranslated
+    match trans.len() == texts.len() {
+        true => Ok(trans),
+        false => Err(BatchLengthError {
+            expected: texts.len(),
+            got: trans.len(),
+        }
+        .into()),
+    }
+}
+
+async fn translate_texts_api(
+    sl: &str,
+    tl: &str,
+    texts: &[String],
+    args: &Args,
+) -> Result<Vec<String>> {
+    let proxy = args.proxy.as_str();
+    let auth_key = args.auth_key.as_str();
+    match args.api.as_str() {
+        "google" => translate_batch(sl, tl, texts, proxy).await,
+        "googlecloud" => {
+            let format = &args.input_format;
+            translate_batch_googlecloud(sl, tl, texts, format, proxy, auth_key, &args.endpoint)
+                .await
+        }
+        "deepl" | "deeplpro" => {
+            translate_batch_deepl(sl, tl, texts, proxy, auth_key, &args.deepl_options()).await
+        }
+        "libretranslate" => {
+            translate_batch_libretranslate(sl, tl, texts, proxy, auth_key, &args.endpoint).await
+        }
+        "llm" => translate_batch_llm(sl, tl, texts, proxy, auth_key, &args.llm_options()).await,
+        "azure" => {
+            translate_batch_azure(sl, tl, texts, proxy, auth_key, &args.region, &args.endpoint)
+                .await
+        }
+        "baidu" => {
+            translate_batch_baidu(sl, tl, texts, proxy, &args.app_id, auth_key, &args.endpoint)
+                .await
+        }
+        "aws" => translate_batch_aws(sl, tl, texts, proxy, &args.aws_options()).await,
+        "exec" => translate_batch_exec(sl, tl, texts, &args.exec_options()).await,
+        "echo" | "pseudo" => {
+            translate_batch_offline(&args.api, texts, &args.offline_options()).await
+        }
+        "youdao" => {
+            translate_batch_youdao(sl, tl, texts, proxy, &args.app_id, auth_key, &args.endpoint)
+                .await
+        }
+        _ => Err(UnsupportApiError.into()),
+    }
+}
+
+#[derive(Debug, Serialize)]
 pub struct TranslateResult {
     orig: String,
     trans: String,
     alter: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dict: Option<Dictionary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    romanization: Option<Romanization>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    correction: Option<Correction>,
+    /// The source language found by the provider
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detected_lang: Option<String>,
+}
+
+/// The "did you mean" spelling of the text
+#[derive(Debug, Serialize)]
+pub struct Correction {
+    /// The misspelled text
+    orig: String,
+    text: String,
+    /// The text has no dictionary entry, `--auto-correct` tries the correction
+    confident: bool,
+    /// The translation is of the corrected text
+    applied: bool,
+}
+
+/// Latin transliteration (pinyin, romaji...) of the text, empty when it is latin already
+#[derive(Debug, Default, Serialize)]
+pub struct Romanization {
+    orig: String,
+    trans: String,
+}
+
+impl Romanization {
+    /// `nihon → rìběn`
+    fn text(&self) -> String {
+        match (self.orig.is_empty(), self.trans.is_empty()) {
+            (false, false) => format!("{} → {}", self.orig, self.trans),
+            (false, true) => self.orig.clone(),
+            _ => self.trans.clone(),
+        }
+    }
+}
+
+/// Dictionary data of a single word, from the providers which have a dictionary
+#[derive(Debug, Default, Serialize)]
+pub struct Dictionary {
+    phonetic: Vec<Phonetic>,
+    explains: Vec<String>,
+    web: Vec<WebPhrase>,
+    /// Grouped by the part of speech
+    entries: Vec<DictionaryEntry>,
+    /// The headword is marked with `<b></b>`
+    examples: Vec<String>,
+}
+
+/// The meanings of a word as one part of speech
+#[derive(Debug, Default, Serialize)]
+pub struct DictionaryEntry {
+    /// noun, verb...
+    pos: String,
+    translations: Vec<ReverseTranslation>,
+    definitions: Vec<Definition>,
+    synonyms: Vec<String>,
+}
+
+/// A translation and the source words it translates back to
+#[derive(Debug, Serialize)]
+pub struct ReverseTranslation {
+    word: String,
+    reverse: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Definition {
+    gloss: String,
+    example: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Phonetic {
+    /// `us`, `uk` or empty for the general one
+    accent: String,
+    symbol: String,
+}
+
+/// A phrase which contains the word and its translations
+#[derive(Debug, Serialize)]
+pub struct WebPhrase {
+    key: String,
+    value: Vec<String>,
+}
+
+impl Dictionary {
+    /// The card under the `[T]` line, a few items of every kind to keep it compact
+    fn render_card<W: Write>(&self, out: &mut W) -> std::io::Result<()> {
+        for e in &self.entries {
+            let translations: Vec<String> = e
+                .translations
+                .iter()
+                .take(5)
+                .map(|t| match t.reverse.is_empty() {
+                    true => t.word.clone(),
+                    false => format!("{} ({})", t.word, t.reverse.join(", ")),
+                })
+                .collect();
+            writeln!(out, "    {} {}", e.pos.yellow(), translations.join("; "))?;
+            for (i, d) in e.definitions.iter().take(2).enumerate() {
+                writeln!(out, "      {}. {}", i + 1, d.gloss)?;
+                if let Some(example) = &d.example {
+                    writeln!(out, "         \"{}\"", example.italic())?;
+                }
+            }
+            if !e.synonyms.is_empty() {
+                let synonyms: Vec<&str> = e.synonyms.iter().take(5).map(|s| s.as_str()).collect();
+                writeln!(out, "      ≈ {}", synonyms.join(", "))?;
+            }
+        }
+        for example in self.examples.iter().take(3) {
+            writeln!(out, "    e.g. {}", highlight(example))?;
+        }
+        Ok(())
+    }
+
+    /// `us /ˈæp.əl/ uk /ˈæp.l̩/`
+    fn phonetic_text(&self) -> String {
+        let symbols: Vec<String> = self
+            .phonetic
+            .iter()
+            .map(|p| match p.accent.as_str() {
+                "" => format!("/{}/", p.symbol),
+                _ => format!("{} /{}/", p.accent, p.symbol),
+            })
+            .collect();
+        symbols.join(" ")
+    }
+}
+
+/// `an <b>apple</b> pie` => `an apple pie` with the bold headword
+fn highlight(example: &str) -> String {
+    let mut output = String::new();
+    let mut rest = example;
+    while let Some(start) = rest.find("<b>") {
+        output.push_str(&rest[..start]);
+        rest = &rest[start + 3..];
+        let end = rest.find("</b>").unwrap_or(rest.len());
+        output.push_str(&rest[..end].bold().to_string());
+        rest = rest.get(end + 4..).unwrap_or("");
+    }
+    output.push_str(rest);
+    output
 }
 
 pub struct TranslateResults<'a> {
     results: Vec<TranslateResult>,
     proxy: &'a str,
     theme: &'a str,
+    /// Show the `[R]` lines
+    romanize: bool,
     start_time: SystemTime,
     end_time: SystemTime,
     index: usize,
 }
 
 impl TranslateResults<'_> {
+    /// Redraw the live line with the tail of the partial translation,
+    /// the line is kept shorter than the terminal so it never wraps
+    fn show_partial(partial: &str) {
+        let width: usize = match std::env::var("COLUMNS") {
+            Ok(c) => c.parse().unwrap_or(80),
+            Err(_) => 80,
+        };
+        // the cjk chars take two columns
+        let char_width = |c: &char| if (*c as u32) < 0x1100 { 1 } else { 2 };
+        let mut tail = Vec::new();
+        let mut tail_width = 0;
+        for c in partial.replace('\n', " ").chars().rev() {
+            tail_width += char_width(&c);
+            if tail_width + 5 > width {
+                break;
+            }
+            tail.push(c);
+        }
+        let tail: String = tail.iter().rev().collect();
+        print!("\r\x1b[2K[{}] {}", "T".green().bold(), tail);
+        let _ = std::io::stdout().flush();
+    }
+
+    fn clear_partial() {
+        print!("\r\x1b[2K");
+        let _ = std::io::stdout().flush();
+    }
+
     fn show(&self, no_original: bool, disable_auto_break: bool) {
+        let mut stdout = std::io::stdout();
+        if let Err(e) = self.render(&mut stdout, no_original, disable_auto_break) {
+            println!("show result failed: {}", e);
+        }
+    }
+
+    /// Write the title and the result lines, the tests render into a buffer
+    fn render<W: Write>(
+        &self,
+        out: &mut W,
+        no_original: bool,
+        disable_auto_break: bool,
+    ) -> std::io::Result<()> {
         let start_time = self.start_time;
         let end_time = self.end_time;
         let index = self.index;
@@ -151,7 +902,7 @@ impl TranslateResults<'_> {
         let t_str = "Translate";
         let theme = self.theme;
 
-        if result_vec.len() > 0 {
+        if !result_vec.is_empty() {
             let index_str = format!("[{}]", index);
             let dt_str = format!("({})", dt_str);
             let dura_str = format!("=>{:.3}s", duration.as_secs_f32());
@@ -166,7 +917,7 @@ impl TranslateResults<'_> {
                         dura_str.on_truecolor(124, 176, 250),  // blue
                     )
                 }
-                "dark" | _ => {
+                _ => {
                     format!(
                         "{}{}{}{}{}",
                         ">>>".truecolor(243, 56, 42),       // red
@@ -184,12 +935,22 @@ impl TranslateResults<'_> {
                     "light" => {
                         title = format!("{}{}", title, "=>proxy".on_truecolor(245, 125, 197))
                     }
-                    "dark" | _ => {
-                        title = format!("{}{}", title, "=>proxy".truecolor(245, 125, 197))
-                    }
+                    _ => title = format!("{}{}", title, "=>proxy".truecolor(245, 125, 197)),
                 },
             }
-            println!("{}", title);
+            // the translation is of the corrected text, `=>helo→hello`
+            let applied = result_vec
+                .iter()
+                .filter_map(|v| v.correction.as_ref())
+                .find(|c| c.applied);
+            if let Some(c) = applied {
+                let corrected = format!("=>{}→{}", c.orig, c.text);
+                title = match theme {
+                    "light" => format!("{}{}", title, corrected.on_truecolor(250, 200, 90)),
+                    _ => format!("{}{}", title, corrected.truecolor(250, 200, 90)),
+                };
+            }
+            writeln!(out, "{}", title)?;
             match disable_auto_break {
                 true => {
                     let mut original_text = String::new();
@@ -205,12 +966,44 @@ impl TranslateResults<'_> {
                     match no_original {
                         true => (),
                         _ => {
-                            println!("[{}] {}", "O".bright_blue().bold(), &original_text);
+                            writeln!(out, "[{}] {}", "O".bright_blue().bold(), &original_text)?;
+                        }
+                    }
+                    writeln!(out, "[{}] {}", "T".green().bold(), &translate_text)?;
+                    if self.romanize {
+                        let romanization: Vec<String> = result_vec
+                            .iter()
+                            .filter_map(|v| v.romanization.as_ref())
+                            .map(|r| r.text())
+                            .collect();
+                        if !romanization.is_empty() {
+                            writeln!(out, "[{}] {}", "R".blue().bold(), romanization.join(" "))?;
+                        }
+                    }
+                    for v in result_vec {
+                        if let Some(c) = v.correction.as_ref().filter(|c| !c.applied) {
+                            writeln!(out, "[{}] {}", "?".yellow().bold(), c.text)?;
                         }
                     }
-                    println!("[{}] {}", "T".green().bold(), &translate_text);
-                    if alter_translate_text.len() > 0 {
-                        println!("[{}] {}", "A".cyan().bold(), &alter_translate_text);
+                    for v in result_vec {
+                        if let Some(dict) = &v.dict {
+                            dict.render_card(out)?;
+                        }
+                    }
+                    if !alter_translate_text.is_empty() {
+                        writeln!(out, "[{}] {}", "A".cyan().bold(), &alter_translate_text)?;
+                    }
+                    for v in result_vec {
+                        if let Some(dict) = &v.dict {
+                            if !dict.phonetic.is_empty() {
+                                writeln!(
+                                    out,
+                                    "[{}] {}",
+                                    "P".magenta().bold(),
+                                    dict.phonetic_text()
+                                )?;
+                            }
+                        }
                     }
                 }
                 _ => {
@@ -218,41 +1011,43 @@ impl TranslateResults<'_> {
                         match no_original {
                             true => (),
                             _ => {
-                                println!("[{}] {}", "O".bright_blue().bold(), v.orig);
+                                writeln!(out, "[{}] {}", "O".bright_blue().bold(), v.orig)?;
                             }
                         }
-                        println!("[{}] {}", "T".green().bold(), v.trans);
+                        writeln!(out, "[{}] {}", "T".green().bold(), v.trans)?;
+                        if let Some(r) = v.romanization.as_ref().filter(|_| self.romanize) {
+                            writeln!(out, "[{}] {}", "R".blue().bold(), r.text())?;
+                        }
+                        if let Some(c) = v.correction.as_ref().filter(|c| !c.applied) {
+                            writeln!(out, "[{}] {}", "?".yellow().bold(), c.text)?;
+                        }
+                        if let Some(dict) = &v.dict {
+                            dict.render_card(out)?;
+                        }
                         for i in 0..v.alter.len() {
-                            println!("[{}] {}", "A".cyan().bold(), v.alter[i]);
+                            writeln!(out, "[{}] {}", "A".cyan().bold(), v.alter[i])?;
+                        }
+                        if let Some(dict) = &v.dict {
+                            if !dict.phonetic.is_empty() {
+                                writeln!(
+                                    out,
+                                    "[{}] {}",
+                                    "P".magenta().bold(),
+                                    dict.phonetic_text()
+                                )?;
+                            }
                         }
                     }
                 }
             }
         }
+        Ok(())
     }
 }
 
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn test() {
-        assert_eq!(2 + 2, 4);
-    }
-}
-
-#[tokio::main]
-async fn main() -> Result<()> {
-    if cfg!(not(target_os = "linux")) && cfg!(not(target_os = "windows")) {
-        return Err(UnsupportOsError.into());
-    }
-
-    let args = Args::parse();
-    let (sl, tl) = standardized_lang(&args.sl, &args.tl, &args.api)?;
+async fn watch(sl: &str, tl: &str, args: &Args) -> Result<()> {
     let fast_mode_sleep_time = Duration::from_secs_f32(args.fast_mode);
-    let clear_mode = match args.clear {
-        0 => false,
-        _ => true,
-    };
+    let clear_mode = args.clear != 0;
 
     // show title
     println!(
@@ -268,8 +1063,8 @@ async fn main() -> Result<()> {
     let mut index: usize = 1;
     loop {
         let text = SelectText::get(args.use_clipboard);
-        let avoid_one_text_translate_twice = if text.len() > 0 {
-            let ret = if text != last_text { true } else { false };
+        let avoid_one_text_translate_twice = if !text.is_empty() {
+            let ret = text != last_text;
 
             if last_text != text {
                 last_text = text.clone();
@@ -291,20 +1086,338 @@ async fn main() -> Result<()> {
                 }
                 clear_count -= 1;
             }
-            let translate_result = translate(
-                &sl,
-                &tl,
-                &text,
-                index,
-                &args.proxy,
-                &args.theme,
-                &args.api,
-                &args.auth_key,
-            )
-            .await?;
+            let translate_result = translate(sl, tl, &text, index, args).await?;
             translate_result.show(args.no_original, args.disable_auto_break);
             index += 1;
         }
         thread::sleep(fast_mode_sleep_time);
     }
 }
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    if cfg!(not(target_os = "linux")) && cfg!(not(target_os = "windows")) {
+        return Err(UnsupportOsError.into());
+    }
+
+    let argv: Vec<String> = std::env::args().collect();
+    let browser_launch = is_browser_launch(&argv);
+    // the browser can not pass our options, they are read from the args file
+    let argv = match browser_launch {
+        true => browser_launch_args(native_args_path().as_deref()),
+        false => argv,
+    };
+    let parse = |argv: &[String]| -> Result<Args> {
+        match browser_launch {
+            true => Ok(Args::try_parse_from(argv)?),
+            false => Ok(Args::parse_from(argv)),
+        }
+    };
+    let args = parse(&argv)?;
+    let args = match args.profile.as_str() {
+        "null" => args,
+        name => parse(&profile_args(&argv, name, config_dir().as_deref())?)?,
+    };
+    let (sl, tl) = match needs_langs(&args.command) {
+        true => resolve_langs(&args.sl, &args.tl, &args).await?,
+        false => (args.sl.clone(), args.tl.clone()),
+    };
+    let (sl, tl) = (sl.as_str(), tl.as_str());
+
+    match &args.command {
+        Some(Commands::Subtitle {
+            input,
+            output,
+            merge_sentences,
+        }) => translate_subtitle_file(sl, tl, input, output, *merge_sentences, &args).await,
+        Some(Commands::Po { input, output }) => {
+            translate_po_file(sl, tl, input, output, &args).await
+        }
+        Some(Commands::I18n {
+            input,
+            output,
+            incremental,
+        }) => translate_resource_file(sl, tl, input, output, *incremental, &args).await,
+        Some(Commands::Serve {
+            bind,
+            token,
+            max_body,
+            allow_origin,
+        }) => serve(bind, token, *max_body, allow_origin, &args).await,
+        Some(Commands::NativeHost { .. }) => native_host(&args).await,
+        Some(Commands::NativeManifest {
+            browser,
+            extension_id,
+            path,
+        }) => native_manifest(browser, extension_id, path),
+        Some(Commands::DeeplDoc {
+            input,
+            output,
+            interval,
+        }) => translate_document_file(sl, tl, input, output, *interval, &args).await,
+        Some(Commands::Usage) => usage_command(&args).await,
+        Some(Commands::Glossary { action }) => glossary_command(action, sl, tl, &args).await,
+        None => watch(sl, tl, &args).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test() {
+        assert_eq!(2 + 2, 4);
+    }
+
+    fn render(
+        results: &TranslateResults,
+        no_original: bool,
+        disable_auto_break: bool,
+    ) -> Vec<String> {
+        colored::control::set_override(false);
+        let mut buf = Vec::new();
+        results
+            .render(&mut buf, no_original, disable_auto_break)
+            .unwrap();
+        String::from_utf8(buf)
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_show_pseudo() {
+        let args = Args::parse_from([
+            "translator-rs",
+            "-a",
+            "pseudo",
+            "-p",
+            "socks5://127.0.0.1:1080",
+        ]);
+        let content = SelectText::filter("Hello world. How are you?");
+        let results = translate("en", "ja", &content, 3, &args).await.unwrap();
+        let lines = render(&results, false, false);
+        assert!(lines[0].starts_with(">>>Translate[3]("));
+        assert!(lines[0].ends_with("s=>proxy"));
+        assert_eq!(
+            lines[1..],
+            [
+                "[O] Hello world. ",
+                "[T] [Ĥéļļö ŵöŕļđ. ~~~] ",
+                "[O] How are you?",
+                "[T] [Ĥöŵ àŕé ýöû? ~~~]",
+            ]
+        );
+
+        let lines = render(&results, true, true);
+        assert_eq!(lines[1..], ["[T] [Ĥéļļö ŵöŕļđ. ~~~] [Ĥöŵ àŕé ýöû? ~~~]"]);
+    }
+
+    #[test]
+    fn test_show_dictionary() {
+        let dict = Dictionary {
+            entries: vec![DictionaryEntry {
+                pos: "noun".to_string(),
+                translations: vec![ReverseTranslation {
+                    word: "苹果".to_string(),
+                    reverse: vec!["apple".to_string()],
+                }],
+                definitions: vec![Definition {
+                    gloss: "the round fruit of a tree".to_string(),
+                    example: Some("apple juice".to_string()),
+                }],
+                synonyms: vec!["pome".to_string()],
+            }],
+            examples: vec!["an <b>apple</b> pie".to_string()],
+            ..Default::default()
+        };
+        let mut results = TranslateResults {
+            results: vec![TranslateResult {
+                orig: "apple".to_string(),
+                trans: "苹果".to_string(),
+                alter: vec!["苹果树".to_string()],
+                dict: Some(dict),
+                romanization: Some(Romanization {
+                    orig: String::new(),
+                    trans: "Píngguǒ".to_string(),
+                }),
+                correction: None,
+                detected_lang: None,
+            }],
+            proxy: "null",
+            theme: "dark",
+            romanize: false,
+            start_time: SystemTime::now(),
+            end_time: SystemTime::now(),
+            index: 1,
+        };
+        assert_eq!(
+            render(&results, false, false)[1..],
+            [
+                "[O] apple",
+                "[T] 苹果",
+                "    noun 苹果 (apple)",
+                "      1. the round fruit of a tree",
+                "         \"apple juice\"",
+                "      ≈ pome",
+                "    e.g. an apple pie",
+                "[A] 苹果树",
+            ]
+        );
+
+        // the [R] line is only shown with --romanize
+        results.romanize = true;
+        let lines = render(&results, false, false);
+        assert_eq!(lines[2..4], ["[T] 苹果", "[R] Píngguǒ"]);
+        let lines = render(&results, true, true);
+        assert_eq!(lines[1..3], ["[T] 苹果", "[R] Píngguǒ"]);
+    }
+
+    #[tokio::test]
+    async fn test_show_correction() {
+        let args = Args::parse_from(["translator-rs", "-a", "echo", "--auto-correct"]);
+        let result = |confident| TranslateResult {
+            orig: "helo".to_string(),
+            trans: "helo".to_string(),
+            alter: Vec::new(),
+            dict: None,
+            romanization: None,
+            correction: Some(Correction {
+                orig: "helo".to_string(),
+                text: "hello".to_string(),
+                confident,
+                applied: false,
+            }),
+            detected_lang: None,
+        };
+        let mut results = TranslateResults {
+            results: apply_correction("en", "en", vec![result(false)], &args).await,
+            proxy: "null",
+            theme: "dark",
+            romanize: false,
+            start_time: SystemTime::now(),
+            end_time: SystemTime::now(),
+            index: 1,
+        };
+        // not sure, the hint is shown under the translation
+        let lines = render(&results, false, false);
+        assert!(lines[0].ends_with('s'));
+        assert_eq!(lines[1..], ["[O] helo", "[T] helo", "[?] hello"]);
+        assert_eq!(render(&results, true, true)[1..], ["[T] helo", "[?] hello"]);
+
+        // a valid word without an entry keeps the hint, echo has no dictionary
+        let unknown = |text: &str| TranslateResult {
+            orig: text.to_string(),
+            trans: text.to_string(),
+            alter: Vec::new(),
+            dict: None,
+            romanization: None,
+            correction: Some(Correction {
+                orig: text.to_string(),
+                text: "soft max".to_string(),
+                confident: true,
+                applied: false,
+            }),
+            detected_lang: None,
+        };
+        results.results = apply_correction("en", "en", vec![unknown("softmax")], &args).await;
+        let lines = render(&results, false, false);
+        assert_eq!(lines[1..], ["[O] softmax", "[T] softmax", "[?] soft max"]);
+
+        // the corrected word has an entry, it is translated and the correction goes to the title
+        let corrected = TranslateResult {
+            orig: "hello".to_string(),
+            trans: "你好".to_string(),
+            alter: Vec::new(),
+            dict: Some(Dictionary::default()),
+            romanization: None,
+            correction: None,
+            detected_lang: None,
+        };
+        results.results = corrected_results(vec![result(true)], vec![corrected]);
+        let lines = render(&results, false, false);
+        assert!(lines[0].ends_with("s=>helo→hello"));
+        assert_eq!(lines[1..3], ["[O] hello", "[T] 你好"]);
+    }
+
+    #[tokio::test]
+    async fn test_translate_texts_length() {
+        let url = test_support::mock_server(|_| {
+            server::HttpResponse::json(200, serde_json::json!({ "translatedText": ["a"] }))
+        })
+        .await;
+        let args = Args::parse_from(["translator-rs", "-a", "libretranslate", "--endpoint", &url]);
+        let texts = vec!["a".to_string(), "b".to_string()];
+        let e = translate_texts("en", "ja", &texts, &args)
+            .await
+            .unwrap_err();
+        assert_eq!(e.to_string(), "the translation has 1 texts instead of 2");
+    }
+
+    #[test]
+    fn test_needs_langs() {
+        let needs = |argv: &[&str]| needs_langs(&Args::parse_from(argv).command);
+        assert!(needs(&["translator-rs"]));
+        assert!(needs(&["translator-rs", "po", "fr.po"]));
+        assert!(needs(&[
+            "translator-rs",
+            "glossary",
+            "create",
+            "terms",
+            "terms.tsv"
+        ]));
+        assert!(!needs(&[
+            "translator-rs",
+            "native-manifest",
+            "--extension-id",
+            "abc"
+        ]));
+        assert!(!needs(&["translator-rs", "glossary", "list"]));
+    }
+
+    #[test]
+    fn test_profile_args() {
+        let dir = std::env::temp_dir().join(format!("translator-rs-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("profiles")).unwrap();
+        std::fs::write(
+            dir.join("profiles").join("papers.args"),
+            "# formal german for the papers\n--api=deepl\n--formality=prefer_more\n--deepl-model=quality_optimized\n--exec-option=a=1\n",
+        )
+        .unwrap();
+        let argv: Vec<String> = [
+            "translator-rs",
+            "--profile",
+            "papers",
+            "--exec-option",
+            "b=2",
+            "po",
+            "de.po",
+            "--formality",
+            "less",
+        ]
+        .iter()
+        .map(|a| a.to_string())
+        .collect();
+        let args = Args::parse_from(profile_args(&argv, "papers", Some(&dir)).unwrap());
+        assert_eq!(args.api, "deepl");
+        assert_eq!(args.deepl_model, "quality_optimized");
+        // the command line wins, the repeated options add up
+        assert_eq!(args.formality, "less");
+        assert_eq!(args.exec_option, vec!["a=1", "b=2"]);
+        assert!(matches!(args.command, Some(Commands::Po { .. })));
+
+        let e = profile_args(&argv, "slides", Some(&dir)).unwrap_err();
+        assert!(e.downcast_ref::<ProfileNotFoundError>().is_some());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_show_failure() {
+        let args = Args::parse_from(["translator-rs", "-a", "echo", "--offline-fail-every", "1"]);
+        let results = translate("en", "ja", "text", 0, &args).await.unwrap();
+        // the failure is printed and nothing is shown
+        assert!(render(&results, false, false).is_empty());
+    }
+}
@@ -0,0 +1,233 @@
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::errors::NativeMessageTooLargeError;
+use crate::errors::UnsupportBrowserError;
+use crate::resolve_langs;
+use crate::translate_content;
+use crate::utils::{config_dir, read_args_file, SelectText};
+use crate::Args;
+
+/// Name of the host in the manifest, the extension connects to it with this name
+pub const HOST_NAME: &str = "translator_rs";
+/// Chrome limits the message from the host to 1 MiB, the same limit is used for the input
+const MAX_MESSAGE: usize = 1024 * 1024;
+
+#[derive(Debug, Deserialize)]
+struct NativeRequest {
+    /// Returned as it is, the extension uses it to match the responses
+    id: Option<serde_json::Value>,
+    text: String,
+    sl: Option<String>,
+    tl: Option<String>,
+}
+
+/// Read one message, every message is a native-endian u32 length and the utf-8 json,
+/// None means the browser closed the pipe
+pub async fn read_message<R: AsyncRead + Unpin>(
+    reader: &mut R,
+) -> Result<Option<serde_json::Value>> {
+    let mut len_buf = [0u8; 4];
+    match reader.read_exact(&mut len_buf).await {
+        Ok(_) => (),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let len = u32::from_ne_bytes(len_buf) as usize;
+    if len > MAX_MESSAGE {
+        return Err(NativeMessageTooLargeError.into());
+    }
+    let mut buf = vec![0u8; len];
+    reader.read_exact(&mut buf).await?;
+    Ok(Some(serde_json::from_slice(&buf)?))
+}
+
+pub async fn write_message<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    message: &serde_json::Value,
+) -> Result<()> {
+    let buf = message.to_string().into_bytes();
+    if buf.len() > MAX_MESSAGE {
+        return Err(NativeMessageTooLargeError.into());
+    }
+    writer.write_all(&(buf.len() as u32).to_ne_bytes()).await?;
+    writer.write_all(&buf).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+async fn handle_message(message: serde_json::Value, args: &Args) -> serde_json::Value {
+    let id = message
+        .get("id")
+        .cloned()
+        .unwrap_or(serde_json::Value::Null);
+    let req: NativeRequest = match serde_json::from_value(message) {
+        Ok(r) => r,
+        Err(e) => return json!({ "id": id, "error": format!("invalid request: {}", e) }),
+    };
+    let sl = req.sl.as_deref().unwrap_or(&args.sl);
+    let tl = req.tl.as_deref().unwrap_or(&args.tl);
+    let (sl, tl) = match resolve_langs(sl, tl, args).await {
+        Ok(l) => l,
+        Err(e) => return json!({ "id": req.id, "error": e.to_string() }),
+    };
+    let (sl, tl) = (sl.as_str(), tl.as_str());
+    let text = SelectText::filter(&req.text);
+
+    let start_time = SystemTime::now();
+    match translate_content(sl, tl, &text, args).await {
+        Ok(results) => json!({
+            "id": req.id,
+            "api": args.api,
+            "sl": sl,
+            "tl": tl,
+            "duration": start_time.elapsed().unwrap_or_default().as_secs_f32(),
+            "results": results,
+        }),
+        Err(e) => json!({ "id": req.id, "error": format!("translate failed: {}", e) }),
+    }
+}
+
+/// Serve the messages of the browser until it closes stdin,
+/// nothing else may be printed to stdout in this mode
+pub async fn native_host(args: &Args) -> Result<()> {
+    let mut stdin = tokio::io::stdin();
+    let mut stdout = tokio::io::stdout();
+    while let Some(message) = read_message(&mut stdin).await? {
+        let response = handle_message(message, args).await;
+        write_message(&mut stdout, &response).await?;
+    }
+    Ok(())
+}
+
+/// The browser starts the host with the extension origin (chrome)
+/// or the manifest path and the extension id (firefox) as arguments
+pub fn is_browser_launch(args: &[String]) -> bool {
+    match args.get(1) {
+        Some(a) if a.starts_with("chrome-extension://") => true,
+        Some(a) => a.ends_with(".json") && args.len() == 3,
+        None => false,
+    }
+}
+
+/// `~/.config/translator-rs/native-host.args`, `%APPDATA%` on windows
+pub fn native_args_path() -> Option<PathBuf> {
+    Some(config_dir()?.join("native-host.args"))
+}
+
+/// The command line of a host started by the browser, the options come from the args file
+pub fn browser_launch_args(path: Option<&Path>) -> Vec<String> {
+    let mut args = vec!["translator-rs".to_string()];
+    if let Some(options) = path.and_then(read_args_file) {
+        args.extend(options);
+    }
+    args.push("native-host".to_string());
+    args
+}
+
+pub fn native_manifest(browser: &str, extension_id: &str, path: &str) -> Result<()> {
+    let path = match path {
+        "null" => std::env::current_exe()?.display().to_string(),
+        _ => path.to_string(),
+    };
+    let manifest = match browser {
+        "chrome" | "chromium" | "edge" => json!({
+            "name": HOST_NAME,
+            "description": "translator-rs native messaging host",
+            "path": path,
+            "type": "stdio",
+            "allowed_origins": [format!("chrome-extension://{}/", extension_id)],
+        }),
+        "firefox" => json!({
+            "name": HOST_NAME,
+            "description": "translator-rs native messaging host",
+            "path": path,
+            "type": "stdio",
+            "allowed_extensions": [extension_id],
+        }),
+        _ => return Err(UnsupportBrowserError.into()),
+    };
+    println!("{}", serde_json::to_string_pretty(&manifest)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_message_frame() {
+        let mut buf = Vec::new();
+        let message = json!({ "id": 1, "text": "hello" });
+        write_message(&mut buf, &message).await.unwrap();
+        assert_eq!(&buf[..4], &(message.to_string().len() as u32).to_ne_bytes());
+
+        let mut reader = &buf[..];
+        assert_eq!(read_message(&mut reader).await.unwrap(), Some(message));
+        assert_eq!(read_message(&mut reader).await.unwrap(), None);
+
+        let too_large = (MAX_MESSAGE as u32 + 1).to_ne_bytes();
+        assert!(read_message(&mut &too_large[..]).await.is_err());
+    }
+
+    #[test]
+    fn test_browser_launch_args() {
+        use clap::Parser;
+
+        let path = std::env::temp_dir().join(format!("native-host-{}.args", std::process::id()));
+        std::fs::write(
+            &path,
+            "# the provider of the extension\n--api=deepl\n  --auth-key=key:fx\n\n--deepl-context=a b\n",
+        )
+        .unwrap();
+        let args = Args::try_parse_from(browser_launch_args(Some(&path))).unwrap();
+        assert_eq!(args.api, "deepl");
+        assert_eq!(args.auth_key, "key:fx");
+        assert_eq!(args.deepl_context, "a b");
+        assert!(matches!(
+            args.command,
+            Some(crate::Commands::NativeHost { .. })
+        ));
+        std::fs::remove_file(&path).unwrap();
+
+        // no args file, the default options
+        let args = Args::try_parse_from(browser_launch_args(Some(&path))).unwrap();
+        assert_eq!(args.api, "google");
+
+        // a wrapper script passes the options itself and is not a browser launch
+        let argv = [
+            "translator-rs",
+            "-a",
+            "deepl",
+            "native-host",
+            "chrome-extension://abc/",
+        ];
+        let argv: Vec<String> = argv.iter().map(|s| s.to_string()).collect();
+        assert!(!is_browser_launch(&argv));
+        assert_eq!(Args::try_parse_from(argv).unwrap().api, "deepl");
+    }
+
+    #[test]
+    fn test_browser_launch() {
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+        assert!(is_browser_launch(&args(&[
+            "translator-rs",
+            "chrome-extension://abc/"
+        ])));
+        assert!(is_browser_launch(&args(&[
+            "translator-rs",
+            "/home/user/.mozilla/native-messaging-hosts/translator_rs.json",
+            "translator@example.com"
+        ])));
+        assert!(!is_browser_launch(&args(&["translator-rs", "-a", "deepl"])));
+        assert!(!is_browser_launch(&args(&[
+            "translator-rs",
+            "i18n",
+            "en.json"
+        ])));
+    }
+}
@@ -0,0 +1,250 @@
+use anyhow::Result;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crate::errors::OfflineInjectedError;
+use crate::utils::SelectText;
+use crate::TranslateResult;
+
+/// Counts the requests of all the offline providers for the failure injection
+static REQUESTS: AtomicUsize = AtomicUsize::new(0);
+
+/// The options of the echo and pseudo providers
+#[derive(Debug, Clone)]
+pub struct OfflineOptions {
+    /// Sleep before every response, in milliseconds
+    pub latency: u64,
+    /// Fail every n-th request, 0 never fails
+    pub fail_every: usize,
+}
+
+/// Split the text after the sentence ends like google, the spaces stay with the sentence
+pub fn split_segments(text: &str) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        current.push(c);
+        let end = match c {
+            '.' | '!' | '?' => chars.peek().map(|n| n.is_whitespace()).unwrap_or(false),
+            '。' | '！' | '？' => true,
+            _ => false,
+        };
+        if end {
+            while let Some(n) = chars.peek().filter(|n| n.is_whitespace()) {
+                current.push(*n);
+                chars.next();
+            }
+            segments.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        segments.push(current);
+    }
+    segments
+}
+
+fn accent(c: char) -> char {
+    match c {
+        'a' => 'à',
+        'b' => 'ƀ',
+        'c' => 'ç',
+        'd' => 'đ',
+        'e' => 'é',
+        'f' => 'ƒ',
+        'g' => 'ĝ',
+        'h' => 'ĥ',
+        'i' => 'î',
+        'j' => 'ĵ',
+        'k' => 'ķ',
+        'l' => 'ļ',
+        'm' => 'ɱ',
+        'n' => 'ñ',
+        'o' => 'ö',
+        'p' => 'þ',
+        'q' => 'ǫ',
+        'r' => 'ŕ',
+        's' => 'š',
+        't' => 'ţ',
+        'u' => 'û',
+        'v' => 'ṽ',
+        'w' => 'ŵ',
+        'x' => 'ẋ',
+        'y' => 'ý',
+        'z' => 'ž',
+        'A' => 'À',
+        'B' => 'Ɓ',
+        'C' => 'Ç',
+        'D' => 'Đ',
+        'E' => 'É',
+        'F' => 'Ƒ',
+        'G' => 'Ĝ',
+        'H' => 'Ĥ',
+        'I' => 'Î',
+        'J' => 'Ĵ',
+        'K' => 'Ķ',
+        'L' => 'Ļ',
+        'M' => 'Ṁ',
+        'N' => 'Ñ',
+        'O' => 'Ö',
+        'P' => 'Þ',
+        'Q' => 'Ǫ',
+        'R' => 'Ŕ',
+        'S' => 'Š',
+        'T' => 'Ŧ',
+        'U' => 'Û',
+        'V' => 'Ṽ',
+        'W' => 'Ŵ',
+        'X' => 'Ẋ',
+        'Y' => 'Ý',
+        'Z' => 'Ž',
+        _ => c,
+    }
+}
+
+/// `Translate this` => `[Ŧŕàñšļàţé ţĥîš ~~~]`, the text grows about 20% like most
+/// translations do, the placeholders (`%s`, `{name}`) and the tags are kept as they are
+pub fn pseudo(text: &str) -> String {
+    let body = text.trim_end();
+    let trailing = &text[body.len()..];
+    let mut out = String::new();
+    let mut chars = body.chars();
+    let mut keep_until = None;
+    while let Some(c) = chars.next() {
+        if let Some(end) = keep_until {
+            out.push(c);
+            if c == end {
+                keep_until = None;
+            }
+            continue;
+        }
+        match c {
+            '{' => keep_until = Some('}'),
+            '<' => keep_until = Some('>'),
+            '%' => {
+                // %s, %d, %1$s and %(name)s
+                out.push(c);
+                let mut in_name = false;
+                for n in chars.by_ref() {
+                    out.push(n);
+                    match n {
+                        '(' => in_name = true,
+                        ')' => in_name = false,
+                        _ if !in_name && (n.is_ascii_alphabetic() || n == '%') => break,
+                        _ => (),
+                    }
+                }
+                continue;
+            }
+            _ => (),
+        }
+        out.push(match keep_until {
+            Some(_) => c,
+            None => accent(c),
+        });
+    }
+    let expansion = body.chars().count().div_ceil(5);
+    format!("[{} {}]{}", out, "~".repeat(expansion.max(1)), trailing)
+}
+
+async fn inject(options: &OfflineOptions) -> Result<()> {
+    let n = REQUESTS.fetch_add(1, Ordering::SeqCst) + 1;
+    if options.latency > 0 {
+        tokio::time::sleep(Duration::from_millis(options.latency)).await;
+    }
+    if options.fail_every > 0 && n.is_multiple_of(options.fail_every) {
+        return Err(OfflineInjectedError.into());
+    }
+    Ok(())
+}
+
+fn convert(api: &str, text: &str) -> String {
+    match api {
+        "pseudo" => pseudo(text),
+        _ => text.to_string(),
+    }
+}
+
+/// The `echo` and `pseudo` providers, no network is used
+pub async fn translate_offline(
+    api: &str,
+    content: &str,
+    options: &OfflineOptions,
+) -> Result<Vec<TranslateResult>> {
+    inject(options).await?;
+    let content = SelectText::unescape(content);
+    let result_vec = split_segments(&content)
+        .into_iter()
+        .map(|orig| TranslateResult {
+            trans: convert(api, &orig),
+            orig,
+            alter: Vec::new(),
+            dict: None,
+            romanization: None,
+            correction: None,
+            detected_lang: None,
+        })
+        .collect();
+    Ok(result_vec)
+}
+
+pub async fn translate_batch_offline(
+    api: &str,
+    texts: &[String],
+    options: &OfflineOptions,
+) -> Result<Vec<String>> {
+    inject(options).await?;
+    Ok(texts.iter().map(|t| convert(api, t)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pseudo() {
+        assert_eq!(pseudo("Translate this"), "[Ŧŕàñšļàţé ţĥîš ~~~]");
+        assert_eq!(pseudo("Hi %s, {name}!"), "[Ĥî %s, {name}! ~~~]");
+        assert_eq!(pseudo("<b>bold</b> %1$d "), "[<b>ƀöļđ</b> %1$d ~~~~] ");
+        assert_eq!(
+            crate::utils::extract_placeholders(&pseudo("%(count)d of {total}")),
+            crate::utils::extract_placeholders("%(count)d of {total}")
+        );
+    }
+
+    #[test]
+    fn test_split_segments() {
+        assert_eq!(
+            split_segments("Hello world. How are you?  Fine, e.g. 3.5 ok"),
+            vec!["Hello world. ", "How are you?  ", "Fine, e.g. ", "3.5 ok"]
+        );
+        assert_eq!(split_segments("你好。世界"), vec!["你好。", "世界"]);
+    }
+
+    #[tokio::test]
+    async fn test_translate_offline() {
+        let options = OfflineOptions {
+            latency: 0,
+            fail_every: 0,
+        };
+        let r = translate_offline("echo", "a%26b. c", &options)
+            .await
+            .unwrap();
+        assert_eq!(r[0].orig, "a&b. ");
+        assert_eq!(r[0].trans, "a&b. ");
+        assert_eq!(r[1].trans, "c");
+
+        let texts = vec!["ab".to_string()];
+        let r = translate_batch_offline("pseudo", &texts, &options)
+            .await
+            .unwrap();
+        assert_eq!(r, vec!["[àƀ ~]"]);
+
+        let options = OfflineOptions {
+            latency: 10,
+            fail_every: 1,
+        };
+        let e = translate_offline("echo", "a", &options).await.unwrap_err();
+        assert!(e.downcast_ref::<OfflineInjectedError>().is_some());
+    }
+}
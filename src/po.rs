@@ -0,0 +1,362 @@
+use anyhow::Result;
+use colored::Colorize;
+use std::fs;
+use std::path::Path;
+
+use crate::translate_texts;
+use crate::utils::extract_placeholders;
+use crate::Args;
+
+#[derive(Debug, Clone, Default)]
+pub struct PoEntry {
+    /// Original lines, written back untouched if the entry is not translated
+    raw: Vec<String>,
+    /// Translator, extracted and reference comments
+    comments: Vec<String>,
+    flags: Vec<String>,
+    msgctxt: Option<String>,
+    msgid: String,
+    msgid_plural: Option<String>,
+    msgstr: Vec<String>,
+    obsolete: bool,
+    translated: bool,
+}
+
+impl PoEntry {
+    fn is_header(&self) -> bool {
+        self.msgid.is_empty() && self.msgctxt.is_none()
+    }
+    fn need_translate(&self) -> bool {
+        !self.obsolete && !self.is_header() && self.msgstr.iter().all(|s| s.is_empty())
+    }
+    fn write(&self) -> String {
+        if !self.translated {
+            return self.raw.join("\n");
+        }
+        let mut lines = self.comments.clone();
+        if !self.flags.is_empty() {
+            lines.push(format!("#, {}", self.flags.join(", ")));
+        }
+        if let Some(ctxt) = &self.msgctxt {
+            lines.push(format!("msgctxt \"{}\"", escape(ctxt)));
+        }
+        lines.push(format!("msgid \"{}\"", escape(&self.msgid)));
+        match &self.msgid_plural {
+            Some(plural) => {
+                lines.push(format!("msgid_plural \"{}\"", escape(plural)));
+                for (i, s) in self.msgstr.iter().enumerate() {
+                    lines.push(format!("msgstr[{}] \"{}\"", i, escape(s)));
+                }
+            }
+            None => lines.push(format!("msgstr \"{}\"", escape(&self.msgstr[0]))),
+        }
+        lines.join("\n")
+    }
+}
+
+fn unescape(input: &str) -> String {
+    let mut output = String::new();
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => output.push('\n'),
+                Some('t') => output.push('\t'),
+                Some('r') => output.push('\r'),
+                Some(c) => output.push(c),
+                None => (),
+            },
+            _ => output.push(c),
+        }
+    }
+    output
+}
+
+fn escape(input: &str) -> String {
+    input
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+        .replace('\r', "\\r")
+}
+
+/// The quoted string at the end of a line like `msgid "xxx"`
+fn quoted(line: &str) -> String {
+    match (line.find('"'), line.rfind('"')) {
+        (Some(s), Some(e)) if e > s => unescape(&line[s + 1..e]),
+        _ => String::new(),
+    }
+}
+
+pub fn parse(content: &str) -> Vec<PoEntry> {
+    let content = content.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    let mut entries = Vec::new();
+    for block in content.split("\n\n") {
+        let block = block.trim_matches('\n');
+        if block.is_empty() {
+            continue;
+        }
+        let mut entry = PoEntry {
+            raw: block.lines().map(|l| l.to_string()).collect(),
+            ..Default::default()
+        };
+        // the keyword which the continuation lines belong to
+        let mut current: Option<(String, usize)> = None;
+        for line in block.lines() {
+            let line = line.trim();
+            if let Some(flags) = line.strip_prefix("#,") {
+                entry.flags = flags.split(',').map(|f| f.trim().to_string()).collect();
+                continue;
+            } else if line.starts_with("#~") {
+                entry.obsolete = true;
+                entry.comments.push(line.to_string());
+                continue;
+            } else if line.starts_with('#') {
+                entry.comments.push(line.to_string());
+                continue;
+            }
+            let value = quoted(line);
+            if line.starts_with('"') {
+                match &current {
+                    Some((k, _)) if k == "msgctxt" => entry
+                        .msgctxt
+                        .get_or_insert_with(String::new)
+                        .push_str(&value),
+                    Some((k, _)) if k == "msgid" => entry.msgid.push_str(&value),
+                    Some((k, _)) if k == "msgid_plural" => entry
+                        .msgid_plural
+                        .get_or_insert_with(String::new)
+                        .push_str(&value),
+                    Some((_, n)) => entry.msgstr[*n].push_str(&value),
+                    None => (),
+                }
+            } else if line.starts_with("msgctxt") {
+                entry.msgctxt = Some(value);
+                current = Some(("msgctxt".to_string(), 0));
+            } else if line.starts_with("msgid_plural") {
+                entry.msgid_plural = Some(value);
+                current = Some(("msgid_plural".to_string(), 0));
+            } else if line.starts_with("msgid") {
+                entry.msgid = value;
+                current = Some(("msgid".to_string(), 0));
+            } else if line.starts_with("msgstr") {
+                // msgstr "xxx" or msgstr[n] "xxx"
+                let n = match (line.find('['), line.find(']')) {
+                    (Some(s), Some(e)) if e > s => line[s + 1..e].parse().unwrap_or(0),
+                    _ => 0,
+                };
+                if entry.msgstr.len() <= n {
+                    entry.msgstr.resize(n + 1, String::new());
+                }
+                entry.msgstr[n] = value;
+                current = Some(("msgstr".to_string(), n));
+            }
+        }
+        entries.push(entry);
+    }
+    entries
+}
+
+pub fn write(entries: &[PoEntry]) -> String {
+    let blocks: Vec<String> = entries.iter().map(|e| e.write()).collect();
+    blocks.join("\n\n") + "\n"
+}
+
+/// Number of plural forms from the `Plural-Forms` header, 2 if it is missing
+fn plural_forms(entries: &[PoEntry]) -> usize {
+    let header = match entries.iter().find(|e| e.is_header()) {
+        Some(h) => h.msgstr.concat(),
+        None => return 2,
+    };
+    for line in header.lines() {
+        if let Some(forms) = line.trim().strip_prefix("Plural-Forms:") {
+            for part in forms.split(';') {
+                if let Some(n) = part.trim().strip_prefix("nplurals=") {
+                    return n.trim().parse().unwrap_or(2);
+                }
+            }
+        }
+    }
+    2
+}
+
+/// Machine translation is accepted only if all the placeholders survive
+pub fn placeholders_preserved(orig: &str, trans: &str) -> bool {
+    extract_placeholders(orig) == extract_placeholders(trans)
+}
+
+/// A msgid with line breaks must keep the same number of them, the last guard
+/// against a provider that joins the lines
+pub fn lines_preserved(orig: &str, trans: &str) -> bool {
+    orig.matches('\n').count() == trans.matches('\n').count()
+}
+
+/// The translation is checked against the msgid before it is used
+fn accepted(orig: &str, trans: &str) -> bool {
+    placeholders_preserved(orig, trans) && lines_preserved(orig, trans)
+}
+
+async fn translate_entries(
+    sl: &str,
+    tl: &str,
+    entries: &mut [PoEntry],
+    args: &Args,
+) -> Result<(usize, usize)> {
+    let nplurals = plural_forms(entries);
+    let mut texts = Vec::new();
+    for e in entries.iter().filter(|e| e.need_translate()) {
+        texts.push(e.msgid.clone());
+        if let Some(plural) = &e.msgid_plural {
+            texts.push(plural.clone());
+        }
+    }
+    let trans = translate_texts(sl, tl, &texts, args).await?;
+
+    let mut trans_iter = trans.into_iter();
+    let mut translated = 0;
+    let mut rejected = 0;
+    for e in entries.iter_mut().filter(|e| e.need_translate()) {
+        let singular = trans_iter.next().unwrap_or_default();
+        let mut ok = accepted(&e.msgid, &singular);
+        let msgstr = match &e.msgid_plural {
+            Some(plural) => {
+                let plural_trans = trans_iter.next().unwrap_or_default();
+                ok = ok && accepted(plural, &plural_trans);
+                // msgstr[0] is the singular form, the others use the plural
+                let mut msgstr = vec![singular];
+                msgstr.resize(nplurals.max(1), plural_trans);
+                msgstr
+            }
+            None => vec![singular],
+        };
+        if ok && msgstr.iter().all(|s| !s.is_empty()) {
+            e.msgstr = msgstr;
+            if !e.flags.iter().any(|f| f == "fuzzy") {
+                e.flags.insert(0, "fuzzy".to_string());
+            }
+            e.translated = true;
+            translated += 1;
+        } else {
+            println!("{} {}", "rejected:".yellow(), e.msgid);
+            rejected += 1;
+        }
+    }
+    Ok((translated, rejected))
+}
+
+pub async fn translate_po_file(
+    sl: &str,
+    tl: &str,
+    input: &str,
+    output: &str,
+    args: &Args,
+) -> Result<()> {
+    let path = Path::new(input);
+    let content = fs::read_to_string(path)?;
+    let mut entries = parse(&content);
+
+    let (translated, rejected) = translate_entries(sl, tl, &mut entries, args).await?;
+
+    // the catalog is updated in place like msgmerge does
+    let output = match output {
+        "null" => path.to_path_buf(),
+        _ => Path::new(output).to_path_buf(),
+    };
+    fs::write(&output, write(&entries))?;
+    println!(
+        "{}{}{}{}",
+        "Translated ".green(),
+        format!("{} entries", translated).green().bold(),
+        ", rejected ".green(),
+        format!("{} entries", rejected).yellow().bold(),
+    );
+    println!("{}{}", "Saved to ".green(), output.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PO: &str = r#"msgid ""
+msgstr ""
+"Language: ja\n"
+"Plural-Forms: nplurals=1; plural=0;\n"
+
+#. greeting on the start page
+#: src/main.c:12
+#, c-format
+msgid "Hello %s"
+msgstr ""
+
+msgctxt "menu"
+msgid ""
+"Open "
+"{name}"
+msgstr ""
+
+msgid "%d file"
+msgid_plural "%d files"
+msgstr[0] ""
+msgstr[1] ""
+"#;
+
+    #[test]
+    fn test_parse() {
+        let entries = parse(PO);
+        assert_eq!(entries.len(), 4);
+        assert!(entries[0].is_header());
+        assert_eq!(plural_forms(&entries), 1);
+        assert_eq!(entries[1].comments.len(), 2);
+        assert_eq!(entries[1].flags, vec!["c-format"]);
+        assert_eq!(entries[2].msgctxt.as_deref(), Some("menu"));
+        assert_eq!(entries[2].msgid, "Open {name}");
+        assert_eq!(entries[3].msgid_plural.as_deref(), Some("%d files"));
+        assert!(entries.iter().skip(1).all(|e| e.need_translate()));
+        assert_eq!(write(&entries), PO);
+    }
+
+    #[test]
+    fn test_write_translated() {
+        let mut entries = parse(PO);
+        entries[1].msgstr = vec!["こんにちは %s".to_string()];
+        entries[1].flags.insert(0, "fuzzy".to_string());
+        entries[1].translated = true;
+        assert_eq!(
+            entries[1].write(),
+            "#. greeting on the start page\n#: src/main.c:12\n#, fuzzy, c-format\nmsgid \"Hello %s\"\nmsgstr \"こんにちは %s\""
+        );
+    }
+
+    #[test]
+    fn test_placeholders() {
+        assert!(placeholders_preserved("Hello %s", "你好 %s"));
+        assert!(placeholders_preserved("%1$s of %2$d", "%2$d 中的 %1$s"));
+        assert!(placeholders_preserved("Open {name}", "打开 {name}"));
+        assert!(placeholders_preserved("100%% done", "完成 100%%"));
+        assert!(!placeholders_preserved("Hello %s", "你好 % s"));
+        assert!(!placeholders_preserved("Open {name}", "打开 {名字}"));
+    }
+
+    #[test]
+    fn test_lines() {
+        assert!(accepted("Usage:\n  %s FILE", "用法：\n  %s FILE"));
+        // the line break was joined by the provider
+        assert!(!accepted("Usage:\n  %s FILE", "用法：  %s FILE"));
+        assert!(!lines_preserved("a\n", "a"));
+        assert!(lines_preserved("one line", "一行"));
+    }
+
+    #[tokio::test]
+    async fn test_translate_multiline() {
+        use clap::Parser;
+        let mut entries = parse("msgid \"\"\n\"Usage:\\n\"\n\"  %s FILE\"\nmsgstr \"\"\n");
+        let args = Args::parse_from(["translator-rs", "-a", "pseudo"]);
+        let r = translate_entries("en", "ja", &mut entries, &args)
+            .await
+            .unwrap();
+        assert_eq!(r, (1, 0));
+        assert_eq!(entries[0].msgstr, vec!["[Ûšàĝé:\n  %s ƑÎĻÉ ~~~~]"]);
+    }
+}
@@ -0,0 +1,567 @@
+use anyhow::Result;
+use colored::Colorize;
+use serde::Deserialize;
+use serde_json::json;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::Instant;
+
+use crate::errors::BadRequestError;
+use crate::errors::RequestTimeoutError;
+use crate::errors::RequestTooLargeError;
+use crate::errors::ServeTokenError;
+use crate::googlecloud_api;
+use crate::libretranslate_api;
+use crate::resolve_langs;
+use crate::translate_content;
+use crate::utils::supported_langs;
+use crate::utils::SelectText;
+use crate::Args;
+
+/// Max size of the request line and headers
+const MAX_HEADER: usize = 16 * 1024;
+/// The whole request must arrive in this time, an idle client does not keep the socket
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Default)]
+pub struct HttpRequest {
+    pub method: String,
+    /// The path and the query
+    pub target: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    /// The target without the query
+    pub fn path(&self) -> &str {
+        self.target.split('?').next().unwrap_or("")
+    }
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+pub struct HttpResponse {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn json(status: u16, value: serde_json::Value) -> HttpResponse {
+        HttpResponse {
+            status,
+            content_type: "application/json",
+            body: value.to_string().into_bytes(),
+        }
+    }
+    fn error(status: u16, message: &str) -> HttpResponse {
+        HttpResponse::json(status, json!({ "error": message }))
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        429 => "Too Many Requests",
+        456 => "Quota Exceeded",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        _ => "Unknown",
+    }
+}
+
+/// Read one http/1.1 request, the body is limited to `max_body` bytes
+pub async fn read_request(stream: &mut TcpStream, max_body: usize) -> Result<HttpRequest> {
+    read_request_within(stream, max_body, READ_TIMEOUT).await
+}
+
+async fn read_some(stream: &mut TcpStream, chunk: &mut [u8], deadline: Instant) -> Result<usize> {
+    match tokio::time::timeout_at(deadline, stream.read(chunk)).await {
+        Ok(n) => Ok(n?),
+        Err(_) => Err(RequestTimeoutError.into()),
+    }
+}
+
+async fn read_request_within(
+    stream: &mut TcpStream,
+    max_body: usize,
+    timeout: Duration,
+) -> Result<HttpRequest> {
+    let deadline = Instant::now() + timeout;
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos;
+        }
+        if buf.len() > MAX_HEADER {
+            return Err(RequestTooLargeError.into());
+        }
+        let n = read_some(stream, &mut chunk, deadline).await?;
+        if n == 0 {
+            return Err(BadRequestError.into());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+
+    let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or("").split_whitespace();
+    let method = request_line.next().unwrap_or("").to_string();
+    let target = request_line.next().unwrap_or("");
+    if method.is_empty() || !target.starts_with('/') {
+        return Err(BadRequestError.into());
+    }
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|l| l.split_once(':'))
+        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        .collect();
+    let mut request = HttpRequest {
+        method,
+        target: target.to_string(),
+        headers,
+        body: Vec::new(),
+    };
+
+    let content_length = match request.header("Content-Length") {
+        Some(l) => l.parse::<usize>().map_err(|_| BadRequestError)?,
+        None => 0,
+    };
+    if content_length > max_body {
+        return Err(RequestTooLargeError.into());
+    }
+    let mut body = buf[header_end + 4..].to_vec();
+    while body.len() < content_length {
+        let n = read_some(stream, &mut chunk, deadline).await?;
+        if n == 0 {
+            return Err(BadRequestError.into());
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+    body.truncate(content_length);
+    request.body = body;
+    Ok(request)
+}
+
+/// The cors headers are only sent to an allowed origin, other web pages can not read the response
+pub async fn write_response(
+    stream: &mut TcpStream,
+    response: &HttpResponse,
+    cors_origin: Option<&str>,
+) -> Result<()> {
+    let cors = match cors_origin {
+        Some(origin) => format!(
+            "Access-Control-Allow-Origin: {}\r\nAccess-Control-Allow-Headers: Authorization, Content-Type\r\nAccess-Control-Allow-Methods: GET, POST, OPTIONS\r\nVary: Origin\r\n",
+            origin
+        ),
+        None => String::new(),
+    };
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n",
+        response.status,
+        reason(response.status),
+        response.content_type,
+        response.body.len(),
+        cors
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(&response.body).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+struct TranslateRequest {
+    text: String,
+    sl: Option<String>,
+    tl: Option<String>,
+    /// Replaces `--formality` for this request
+    formality: Option<String>,
+    /// Replaces `--deepl-context` for this request
+    context: Option<String>,
+}
+
+async fn handle_translate(request: &HttpRequest, args: &Args) -> HttpResponse {
+    let req: TranslateRequest = match serde_json::from_slice(&request.body) {
+        Ok(r) => r,
+        Err(e) => return HttpResponse::error(400, &format!("invalid request: {}", e)),
+    };
+    let sl = req.sl.as_deref().unwrap_or(&args.sl);
+    let tl = req.tl.as_deref().unwrap_or(&args.tl);
+    let (sl, tl) = match resolve_langs(sl, tl, args).await {
+        Ok(l) => l,
+        Err(e) => return HttpResponse::error(400, &e.to_string()),
+    };
+    let (sl, tl) = (sl.as_str(), tl.as_str());
+    let text = SelectText::filter(&req.text);
+    if text.is_empty() {
+        return HttpResponse::error(400, "empty text");
+    }
+
+    let mut args = args.clone();
+    if let Some(f) = req.formality {
+        args.formality = f;
+    }
+    if let Some(c) = req.context {
+        args.deepl_context = c;
+    }
+
+    let start_time = SystemTime::now();
+    match translate_content(sl, tl, &text, &args).await {
+        Ok(results) => {
+            let duration = start_time.elapsed().unwrap_or_default();
+            HttpResponse::json(
+                200,
+                json!({
+                    "api": args.api,
+                    "sl": sl,
+                    "tl": tl,
+                    "duration": duration.as_secs_f32(),
+                    "results": results,
+                }),
+            )
+        }
+        Err(e) => HttpResponse::error(502, &format!("translate failed: {}", e)),
+    }
+}
+
+async fn handle_languages(args: &Args) -> HttpResponse {
+    if args.api == "libretranslate" {
+        // the self-hosted instance knows which languages are installed
+        return match libretranslate_api::languages(&args.proxy, &args.auth_key, &args.endpoint)
+            .await
+        {
+            Ok(langs) => HttpResponse::json(200, json!({ "api": args.api, "languages": langs })),
+            Err(e) => HttpResponse::error(502, &e.to_string()),
+        };
+    }
+    if args.api == "googlecloud" {
+        // the full list of the api, with the english names
+        return match googlecloud_api::languages("en", &args.proxy, &args.auth_key, &args.endpoint)
+            .await
+        {
+            Ok(langs) => HttpResponse::json(200, json!({ "api": args.api, "languages": langs })),
+            Err(e) => HttpResponse::error(502, &e.to_string()),
+        };
+    }
+    match supported_langs(&args.api) {
+        Ok((langs, _)) => {
+            let langs: Vec<serde_json::Value> = langs
+                .iter()
+                .map(|(name, code)| json!({ "name": name, "code": code }))
+                .collect();
+            HttpResponse::json(200, json!({ "api": args.api, "languages": langs }))
+        }
+        Err(e) => HttpResponse::error(500, &e.to_string()),
+    }
+}
+
+/// The `Origin` of the request when it is in `--allow-origin`, `*` allows every origin
+fn cors_origin<'a>(request: &'a HttpRequest, allow_origins: &[String]) -> Option<&'a str> {
+    let origin = request.header("Origin")?;
+    allow_origins
+        .iter()
+        .any(|o| o == "*" || o == origin)
+        .then_some(origin)
+}
+
+/// Compare the whole token, the time does not tell how much of it is right
+fn token_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// `localhost:8787` => `localhost`, `[::1]:8787` => `::1`
+fn host_name(host: &str) -> &str {
+    match host.strip_prefix('[') {
+        Some(h) => h.split(']').next().unwrap_or(h),
+        None => match host.rsplit_once(':') {
+            Some((name, _)) if !name.contains(':') => name,
+            _ => host,
+        },
+    }
+}
+
+/// A dns rebinding page sends its own name, only the loopback names and the listening
+/// address are accepted, any address when the server listens on all of them
+fn host_allowed(host: &str, local_addr: &SocketAddr) -> bool {
+    let name = host_name(host);
+    if name.eq_ignore_ascii_case("localhost") {
+        return true;
+    }
+    match name.parse::<IpAddr>() {
+        Ok(ip) => ip.is_loopback() || ip == local_addr.ip() || local_addr.ip().is_unspecified(),
+        Err(_) => false,
+    }
+}
+
+/// A form post of another page skips the cors preflight, only json is accepted
+fn is_json(request: &HttpRequest) -> bool {
+    request
+        .header("Content-Type")
+        .and_then(|t| t.split(';').next())
+        .is_some_and(|t| t.trim().eq_ignore_ascii_case("application/json"))
+}
+
+async fn route(
+    request: &HttpRequest,
+    token: &str,
+    local_addr: &SocketAddr,
+    args: &Args,
+) -> HttpResponse {
+    match request.header("Host") {
+        Some(host) if host_allowed(host, local_addr) => {}
+        _ => return HttpResponse::error(403, "host not allowed"),
+    }
+    if request.method == "OPTIONS" {
+        // cors preflight
+        return HttpResponse {
+            status: 204,
+            content_type: "text/plain",
+            body: Vec::new(),
+        };
+    }
+    if request.path() != "/health" && token != "null" {
+        let expected = format!("Bearer {}", token);
+        let given = request.header("Authorization").unwrap_or("");
+        if !token_eq(given.as_bytes(), expected.as_bytes()) {
+            return HttpResponse::error(401, "invalid or missing bearer token");
+        }
+    }
+    match (request.method.as_str(), request.path()) {
+        ("GET", "/health") => HttpResponse::json(200, json!({ "status": "ok", "api": args.api })),
+        ("GET", "/languages") => handle_languages(args).await,
+        ("POST", "/translate") if !is_json(request) => {
+            HttpResponse::error(415, "the content type must be application/json")
+        }
+        ("POST", "/translate") => handle_translate(request, args).await,
+        (_, "/health") | (_, "/languages") | (_, "/translate") => {
+            HttpResponse::error(405, "method not allowed")
+        }
+        _ => HttpResponse::error(404, "not found"),
+    }
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    token: &str,
+    max_body: usize,
+    allow_origins: &[String],
+    local_addr: &SocketAddr,
+    args: &Args,
+) {
+    let (response, origin) = match read_request(&mut stream, max_body).await {
+        Ok(request) => {
+            let origin = cors_origin(&request, allow_origins).map(String::from);
+            (route(&request, token, local_addr, args).await, origin)
+        }
+        Err(e) if e.downcast_ref::<RequestTooLargeError>().is_some() => {
+            (HttpResponse::error(413, &e.to_string()), None)
+        }
+        Err(e) if e.downcast_ref::<RequestTimeoutError>().is_some() => {
+            (HttpResponse::error(408, &e.to_string()), None)
+        }
+        Err(e) => (HttpResponse::error(400, &e.to_string()), None),
+    };
+    if let Err(e) = write_response(&mut stream, &response, origin.as_deref()).await {
+        println!("write response failed: {}", e);
+    }
+}
+
+pub async fn serve(
+    bind: &str,
+    token: &str,
+    max_body: usize,
+    allow_origins: &[String],
+    args: &Args,
+) -> Result<()> {
+    let listener = TcpListener::bind(bind).await?;
+    let local_addr = listener.local_addr()?;
+    // the other machines of the network must not use the api key freely
+    if token == "null" && !local_addr.ip().is_loopback() {
+        return Err(ServeTokenError(local_addr.to_string()).into());
+    }
+    println!(
+        "{}{}{}{}",
+        "Working with ".green(),
+        args.api.green().bold(),
+        " on http://".green(),
+        local_addr.to_string().green().bold()
+    );
+    let args = Arc::new(args.clone());
+    let token = Arc::new(token.to_string());
+    let allow_origins = Arc::new(allow_origins.to_vec());
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let args = args.clone();
+        let token = token.clone();
+        let allow_origins = allow_origins.clone();
+        tokio::spawn(async move {
+            handle_connection(stream, &token, max_body, &allow_origins, &local_addr, &args).await;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    async fn send(request: &str, token: &str) -> String {
+        send_from(request, token, &[]).await
+    }
+
+    async fn send_from(request: &str, token: &str, allow_origins: &[&str]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let token = token.to_string();
+        let allow_origins: Vec<String> = allow_origins.iter().map(|o| o.to_string()).collect();
+        let handle = tokio::spawn(async move {
+            let args = Args::parse_from(["translator-rs"]);
+            let (stream, _) = listener.accept().await.unwrap();
+            handle_connection(stream, &token, 64, &allow_origins, &addr, &args).await;
+        });
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        handle.await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_health_and_languages() {
+        let response = send("GET /health HTTP/1.1\r\nHost: localhost\r\n\r\n", "secret").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.ends_with(r#"{"status":"ok","api":"google"}"#));
+
+        let response = send("GET /languages HTTP/1.1\r\nHost: localhost\r\n\r\n", "null").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains(r#"{"name":"Japanese","code":"ja"}"#));
+
+        let response = send(
+            "GET /nothing HTTP/1.1\r\nHost: 127.0.0.1:8787\r\n\r\n",
+            "null",
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 404"));
+    }
+
+    #[tokio::test]
+    async fn test_token_and_size_limit() {
+        let response = send(
+            "GET /languages HTTP/1.1\r\nHost: localhost\r\n\r\n",
+            "secret",
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 401"));
+        let response = send(
+            "GET /languages HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer secret\r\n\r\n",
+            "secret",
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 200"));
+        let response = send(
+            "GET /languages HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer secreT\r\n\r\n",
+            "secret",
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 401"));
+
+        let body = format!(r#"{{"text": "{}"}}"#, "a".repeat(100));
+        let request = format!(
+            "POST /translate HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let response = send(&request, "null").await;
+        assert!(response.starts_with("HTTP/1.1 413"));
+
+        let request = "POST /translate HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: 9\r\n\r\nnot json!";
+        let response = send(request, "null").await;
+        assert!(response.starts_with("HTTP/1.1 400"));
+    }
+
+    #[tokio::test]
+    async fn test_cors() {
+        let request =
+            "GET /health HTTP/1.1\r\nHost: [::1]:8787\r\nOrigin: https://example.com\r\n\r\n";
+        // no origin is allowed by default, a web page can not read the response
+        let response = send(request, "null").await;
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(!response.contains("Access-Control-Allow-Origin"));
+
+        let response = send_from(request, "null", &["https://zotero.org"]).await;
+        assert!(!response.contains("Access-Control-Allow-Origin"));
+        let response = send_from(request, "null", &["https://example.com"]).await;
+        assert!(response.contains("Access-Control-Allow-Origin: https://example.com\r\n"));
+        let response = send_from(request, "null", &["*"]).await;
+        assert!(response.contains("Access-Control-Allow-Origin: https://example.com\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_host_and_content_type() {
+        // a dns rebinding page
+        let response = send(
+            "GET /health HTTP/1.1\r\nHost: evil.example:8787\r\n\r\n",
+            "null",
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 403"));
+        let response = send("GET /health HTTP/1.1\r\n\r\n", "null").await;
+        assert!(response.starts_with("HTTP/1.1 403"));
+
+        // a form post skips the cors preflight
+        let request = "POST /translate HTTP/1.1\r\nHost: localhost\r\nContent-Type: text/plain\r\nContent-Length: 14\r\n\r\n{\"text\": \"hi\"}";
+        let response = send(request, "null").await;
+        assert!(response.starts_with("HTTP/1.1 415"));
+
+        let local: SocketAddr = "192.168.1.2:8787".parse().unwrap();
+        assert!(host_allowed("192.168.1.2:8787", &local));
+        assert!(host_allowed("LOCALHOST", &local));
+        assert!(!host_allowed("192.168.1.3", &local));
+        let all: SocketAddr = "0.0.0.0:8787".parse().unwrap();
+        assert!(host_allowed("192.168.1.3:8787", &all));
+        assert!(!host_allowed("translate.example", &all));
+    }
+
+    #[tokio::test]
+    async fn test_idle_client() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            read_request_within(&mut stream, 64, Duration::from_millis(100)).await
+        });
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client.write_all(b"GET /health HTTP/1.1\r\n").await.unwrap();
+        let e = handle.await.unwrap().unwrap_err();
+        assert!(e.downcast_ref::<RequestTimeoutError>().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_token_required() {
+        let args = Args::parse_from(["translator-rs"]);
+        let e = serve("0.0.0.0:0", "null", 64, &[], &args)
+            .await
+            .unwrap_err();
+        assert!(e.downcast_ref::<ServeTokenError>().is_some());
+    }
+}
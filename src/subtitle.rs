@@ -0,0 +1,277 @@
+use anyhow::Result;
+use colored::Colorize;
+use std::fs;
+use std::path::Path;
+
+use crate::errors::UnsupportSubtitleError;
+use crate::translate_texts;
+use crate::Args;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubtitleFormat {
+    Srt,
+    Vtt,
+}
+
+#[derive(Debug, Clone)]
+pub struct Cue {
+    /// Numbering of srt or the optional identifier of vtt
+    id: Option<String>,
+    /// Timing line, written back untouched
+    timing: String,
+    text: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Block {
+    Cue(Cue),
+    /// Header, NOTE, STYLE and REGION blocks of vtt
+    Other(String),
+}
+
+pub fn parse(content: &str) -> Vec<Block> {
+    let content = content.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    let mut blocks = Vec::new();
+    for raw in content.split("\n\n") {
+        let raw = raw.trim_matches('\n');
+        if raw.is_empty() {
+            continue;
+        }
+        let lines: Vec<&str> = raw.lines().collect();
+        let block = match lines.iter().position(|l| l.contains("-->")) {
+            // the timing line is the first or the second line of a cue
+            Some(pos) if pos <= 1 && !lines[0].starts_with("NOTE") => Block::Cue(Cue {
+                id: match pos {
+                    1 => Some(lines[0].to_string()),
+                    _ => None,
+                },
+                timing: lines[pos].to_string(),
+                text: lines[pos + 1..].iter().map(|l| l.to_string()).collect(),
+            }),
+            _ => Block::Other(raw.to_string()),
+        };
+        blocks.push(block);
+    }
+    blocks
+}
+
+pub fn write(blocks: &[Block]) -> String {
+    let mut output = Vec::new();
+    for block in blocks {
+        match block {
+            Block::Cue(cue) => {
+                let mut lines = Vec::new();
+                if let Some(id) = &cue.id {
+                    lines.push(id.clone());
+                }
+                lines.push(cue.timing.clone());
+                lines.extend(cue.text.iter().cloned());
+                output.push(lines.join("\n"));
+            }
+            Block::Other(raw) => output.push(raw.clone()),
+        }
+    }
+    output.join("\n\n") + "\n"
+}
+
+fn end_of_sentence(text: &str) -> bool {
+    let text = text.trim_end_matches(['"', '\'', '”', '’', ')', '」', '』']);
+    text.ends_with(['.', '!', '?', '…', '。', '！', '？', ';', '；', '♪'])
+}
+
+/// Split the translation of a merged sentence back into the cues,
+/// the length of every piece follows the length of the original cue text.
+/// Every piece gets one word at least, an empty cue would end the block,
+/// so `None` is returned when there are fewer words than cues
+pub fn resplit(trans: &str, weights: &[usize]) -> Option<Vec<String>> {
+    if weights.len() <= 1 {
+        return Some(vec![trans.to_string()]);
+    }
+    let total: usize = weights.iter().sum::<usize>().max(1);
+    // the target language uses spaces (english, french...) or not (chinese, japanese...)
+    let units: Vec<String> = if trans.contains(' ') {
+        trans.split_whitespace().map(|w| w.to_string()).collect()
+    } else {
+        trans.chars().map(|c| c.to_string()).collect()
+    };
+    let sep = if trans.contains(' ') { " " } else { "" };
+    let units_len: usize = units.iter().map(|u| u.chars().count()).sum();
+    if units.len() < weights.len() {
+        return None;
+    }
+
+    let mut pieces = Vec::new();
+    let mut iter = units.into_iter().peekable();
+    let mut acc_weight = 0;
+    let mut acc_len = 0;
+    for (i, w) in weights.iter().enumerate() {
+        acc_weight += w;
+        let bound = units_len * acc_weight / total;
+        let mut piece = Vec::new();
+        // the words left for the later pieces
+        let later = weights.len() - 1 - i;
+        while let Some(u_len) = iter.peek().map(|u| u.chars().count()) {
+            let last = later == 0;
+            let full = acc_len + u_len / 2 > bound || iter.len() <= later;
+            if !last && !piece.is_empty() && full {
+                break;
+            }
+            acc_len += u_len;
+            piece.push(iter.next().unwrap());
+        }
+        pieces.push(piece.join(sep));
+    }
+    Some(pieces)
+}
+
+async fn translate_cues(
+    sl: &str,
+    tl: &str,
+    blocks: &mut [Block],
+    merge_sentences: bool,
+    args: &Args,
+) -> Result<()> {
+    let mut cues: Vec<&mut Cue> = blocks
+        .iter_mut()
+        .filter_map(|b| match b {
+            Block::Cue(c) if !c.text.is_empty() => Some(c),
+            _ => None,
+        })
+        .collect();
+
+    // every group is translated as one text
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for i in 0..cues.len() {
+        match groups.last_mut() {
+            Some(g) if merge_sentences && !end_of_sentence(&cues[i - 1].text.join(" ")) => {
+                g.push(i)
+            }
+            _ => groups.push(vec![i]),
+        }
+    }
+    let texts: Vec<String> = groups
+        .iter()
+        .map(|g| {
+            g.iter()
+                .map(|&i| cues[i].text.join(" ").trim().to_string())
+                .collect::<Vec<String>>()
+                .join(" ")
+        })
+        .collect();
+    let trans = translate_texts(sl, tl, &texts, args).await?;
+
+    // the cues of a translation too short to split keep their own boundaries
+    let mut single = Vec::new();
+    for (g, t) in groups.iter().zip(trans.iter()) {
+        let weights: Vec<usize> = g
+            .iter()
+            .map(|&i| cues[i].text.join(" ").chars().count())
+            .collect();
+        match resplit(t, &weights) {
+            Some(pieces) => {
+                for (&i, piece) in g.iter().zip(pieces) {
+                    cues[i].text = vec![piece];
+                }
+            }
+            None => single.extend(g.iter().copied()),
+        }
+    }
+    if !single.is_empty() {
+        let texts: Vec<String> = single
+            .iter()
+            .map(|&i| cues[i].text.join(" ").trim().to_string())
+            .collect();
+        let trans = translate_texts(sl, tl, &texts, args).await?;
+        for (&i, t) in single.iter().zip(trans) {
+            cues[i].text = vec![t];
+        }
+    }
+    Ok(())
+}
+
+pub async fn translate_subtitle_file(
+    sl: &str,
+    tl: &str,
+    input: &str,
+    output: &str,
+    merge_sentences: bool,
+    args: &Args,
+) -> Result<()> {
+    let path = Path::new(input);
+    let format = match path.extension().and_then(|e| e.to_str()) {
+        Some(e) if e.eq_ignore_ascii_case("srt") => SubtitleFormat::Srt,
+        Some(e) if e.eq_ignore_ascii_case("vtt") => SubtitleFormat::Vtt,
+        _ => return Err(UnsupportSubtitleError.into()),
+    };
+    let content = fs::read_to_string(path)?;
+    let mut blocks = parse(&content);
+    if format == SubtitleFormat::Vtt
+        && !matches!(blocks.first(), Some(Block::Other(h)) if h.starts_with("WEBVTT"))
+    {
+        return Err(UnsupportSubtitleError.into());
+    }
+
+    let cues_num = blocks.iter().filter(|b| matches!(b, Block::Cue(_))).count();
+    println!(
+        "{}{}{}",
+        "Translating ".green(),
+        format!("{} cues", cues_num).green().bold(),
+        "...".green()
+    );
+    translate_cues(sl, tl, &mut blocks, merge_sentences, args).await?;
+
+    let output = match output {
+        "null" => {
+            let stem = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("subtitle");
+            let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("srt");
+            path.with_file_name(format!("{}.{}.{}", stem, tl, ext))
+        }
+        _ => Path::new(output).to_path_buf(),
+    };
+    fs::write(&output, write(&blocks))?;
+    println!("{}{}", "Saved to ".green(), output.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRT: &str = "1\n00:00:01,000 --> 00:00:02,500\nHello world.\n\n2\n00:00:03,000 --> 00:00:04,000\nThis sentence is\n\n3\n00:00:04,000 --> 00:00:05,000\nsplit in two cues.\n";
+
+    const VTT: &str = "WEBVTT\n\nNOTE a comment\n\nintro\n00:01.000 --> 00:02.000 align:start\nHello\nworld.\n\n00:03.000 --> 00:04.000\nBye.\n";
+
+    #[test]
+    fn test_srt_roundtrip() {
+        let blocks = parse(SRT);
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(write(&blocks), SRT);
+    }
+
+    #[test]
+    fn test_vtt_roundtrip() {
+        let blocks = parse(VTT);
+        assert_eq!(blocks.len(), 4);
+        assert!(matches!(&blocks[2], Block::Cue(c) if c.id.as_deref() == Some("intro")));
+        assert!(matches!(&blocks[3], Block::Cue(c) if c.id.is_none()));
+        assert_eq!(write(&blocks), VTT);
+    }
+
+    #[test]
+    fn test_resplit() {
+        let pieces = resplit("cette phrase est coupée en deux", &[16, 18]).unwrap();
+        assert_eq!(pieces, vec!["cette phrase est", "coupée en deux"]);
+        let pieces = resplit("这个句子被分成两个", &[16, 18]).unwrap();
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(pieces.concat(), "这个句子被分成两个");
+        // every cue gets a word, even a short one
+        let pieces = resplit("un deux trois", &[30, 1, 1]).unwrap();
+        assert_eq!(pieces, vec!["un", "deux", "trois"]);
+        assert!(resplit("oui merci", &[5, 5, 5]).is_none());
+        assert!(end_of_sentence("Hello world."));
+        assert!(!end_of_sentence("This sentence is"));
+    }
+}
@@ -0,0 +1,158 @@
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+
+use crate::server::{read_request, write_response, HttpRequest, HttpResponse};
+
+impl HttpRequest {
+    /// Value of the url query parameter, the value is not percent-decoded
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.target
+            .split_once('?')
+            .map(|(_, q)| q)
+            .unwrap_or("")
+            .split('&')
+            .filter_map(|p| p.split_once('='))
+            .find(|(k, _)| *k == name)
+            .map(|(_, v)| v)
+    }
+    /// The first value of the form field, empty when it is missing
+    pub fn form_param(&self, name: &str) -> String {
+        self.form()
+            .into_iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v)
+            .unwrap_or_default()
+    }
+    /// Percent-decoded pairs of an `application/x-www-form-urlencoded` body
+    pub fn form(&self) -> Vec<(String, String)> {
+        String::from_utf8_lossy(&self.body)
+            .split('&')
+            .filter_map(|p| p.split_once('='))
+            .map(|(k, v)| (percent_decode(k), percent_decode(v)))
+            .collect()
+    }
+}
+
+/// Decode a form or query value, `+` is a space
+pub fn percent_decode(s: &str) -> String {
+    let s = s.replace('+', " ");
+    let bytes = s.as_bytes();
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap();
+                out.push(u8::from_str_radix(hex, 16).unwrap());
+                i += 3;
+            }
+            b => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(out).unwrap()
+}
+
+/// Stand-in server for the api tests, returns the base url
+pub async fn mock_server<F>(handler: F) -> String
+where
+    F: Fn(HttpRequest) -> HttpResponse + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let handler = Arc::new(handler);
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let handler = handler.clone();
+            tokio::spawn(async move {
+                let request = read_request(&mut stream, usize::MAX).await.unwrap();
+                write_response(&mut stream, &handler(request), None)
+                    .await
+                    .unwrap();
+            });
+        }
+    });
+    format!("http://{}", addr)
+}
+
+/// The id of the glossary kept by [`deepl_server`]
+pub const GLOSSARY_ID: &str = "def3a26b-3e84-45b3-84ae-0c0aaf3525f7";
+
+/// Stand-in DeepL api for the key `key`, keeps one glossary in memory,
+/// the texts `over`, `busy`, `down` and `teapot` fail with their status
+/// and the others are echoed, prefixed by the glossary id when there is one
+pub async fn deepl_server() -> String {
+    let store: Mutex<Option<serde_json::Value>> = Mutex::new(None);
+    mock_server(move |request| {
+        if request.header("Authorization") != Some("DeepL-Auth-Key key") {
+            return HttpResponse::json(403, json!({ "message": "Wrong endpoint" }));
+        }
+        let param = |name: &str| request.form_param(name);
+        let mut store = store.lock().unwrap();
+        let entries_path = format!("/v2/glossaries/{}/entries", GLOSSARY_ID);
+        let glossary_path = format!("/v2/glossaries/{}", GLOSSARY_ID);
+        match (request.method.as_str(), request.path()) {
+            ("GET", "/v2/usage") => HttpResponse::json(
+                200,
+                json!({ "character_count": 495000, "character_limit": 500000 }),
+            ),
+            ("POST", "/v2/translate") => {
+                let text = param("text");
+                let text = match param("glossary_id").as_str() {
+                    "" => text,
+                    id => format!("{}:{}", id, text),
+                };
+                match text.as_str() {
+                    "over" => HttpResponse::json(456, json!({ "message": "Quota exceeded" })),
+                    "busy" => HttpResponse::json(429, json!({ "message": "Too many requests" })),
+                    "down" => HttpResponse::json(503, json!({ "message": "Unavailable" })),
+                    "teapot" => HttpResponse::json(418, json!({ "message": "I'm a teapot" })),
+                    _ => HttpResponse::json(
+                        200,
+                        json!({ "translations": [{ "detected_source_language": "EN", "text": text }] }),
+                    ),
+                }
+            }
+            ("POST", "/v2/glossaries") => {
+                assert_eq!(param("entries_format"), "tsv");
+                let g = json!({
+                    "glossary_id": GLOSSARY_ID,
+                    "name": param("name"),
+                    "ready": true,
+                    "source_lang": param("source_lang"),
+                    "target_lang": param("target_lang"),
+                    "creation_time": "2024-01-01T00:00:00.000000Z",
+                    "entry_count": param("entries").lines().count(),
+                });
+                *store = Some(g.clone());
+                HttpResponse::json(201, g)
+            }
+            ("GET", "/v2/glossaries") => {
+                let glossaries: Vec<serde_json::Value> = store.iter().cloned().collect();
+                HttpResponse::json(200, json!({ "glossaries": glossaries }))
+            }
+            ("GET", p) if p == entries_path && store.is_some() => HttpResponse {
+                status: 200,
+                content_type: "text/tab-separated-values",
+                body: b"model\tModell\n".to_vec(),
+            },
+            ("GET", p) if p == glossary_path && store.is_some() => {
+                HttpResponse::json(200, store.clone().unwrap())
+            }
+            ("DELETE", p) if p == glossary_path && store.is_some() => {
+                *store = None;
+                HttpResponse {
+                    status: 204,
+                    content_type: "text/plain",
+                    body: Vec::new(),
+                }
+            }
+            _ => HttpResponse::json(404, json!({ "message": "Glossary not found" })),
+        }
+    })
+    .await
+}
@@ -1,9 +1,11 @@
 use anyhow::Result;
 use reqwest::Proxy;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 use crate::errors::UnsupportApiError;
 
+#[allow(clippy::len_zero, clippy::needless_return)]
 fn get_clipboard_text_linux() -> Result<String> {
     let output = match Command::new("xsel").arg("-b").output() {
         Ok(o) => o,
@@ -18,6 +20,7 @@ fn get_clipboard_text_linux() -> Result<String> {
     }
 }
 
+#[allow(clippy::len_zero, clippy::needless_return)]
 fn get_clipboard_text_windows() -> Result<String> {
     let output = match Command::new("powershell")
         .args(["-Command", "Get-Clipboard"])
@@ -45,6 +48,7 @@ pub fn get_clipboard() -> Result<String> {
     }
 }
 
+#[allow(clippy::len_zero, clippy::needless_return)]
 fn get_select_text_linux() -> Result<String> {
     let output = match Command::new("xsel").output() {
         Ok(o) => o,
@@ -70,26 +74,36 @@ pub fn get_select_text() -> Result<String> {
 pub struct SelectText {}
 
 impl SelectText {
-    pub fn get(use_clipboard: bool) -> String {
-        fn filter(content: &str) -> String {
-            let x = content.trim();
-            let x = match x.strip_prefix(".") {
-                Some(x) => x,
-                _ => x,
-            };
-            let x = match x.strip_prefix(",") {
-                Some(x) => x,
-                _ => x,
-            };
-            x.replace("-\n", "")
-                .replace("%", "%25")
-                .replace("&", "%26")
-                .replace("#", "%23")
-                .replace("\n", " ")
-                .trim()
-                .to_string()
-        }
+    /// Clean the selected text and escape it for the url query
+    pub fn filter(content: &str) -> String {
+        let x = content.trim();
+        let x = match x.strip_prefix(".") {
+            Some(x) => x,
+            _ => x,
+        };
+        let x = match x.strip_prefix(",") {
+            Some(x) => x,
+            _ => x,
+        };
+        x.replace("-\n", "")
+            .replace("%", "%25")
+            .replace("&", "%26")
+            .replace("#", "%23")
+            .replace("\n", " ")
+            .trim()
+            .to_string()
+    }
 
+    /// Undo the url escaping of `filter` for the apis which take a json body
+    pub fn unescape(content: &str) -> String {
+        content
+            .replace("%26", "&")
+            .replace("%23", "#")
+            .replace("%25", "%")
+    }
+
+    #[allow(clippy::let_and_return)]
+    pub fn get(use_clipboard: bool) -> String {
         match use_clipboard {
             true => {
                 let t = match get_clipboard() {
@@ -99,7 +113,7 @@ impl SelectText {
                         "".to_string()
                     }
                 };
-                let ft = filter(&t);
+                let ft = SelectText::filter(&t);
                 ft
             }
             false => {
@@ -113,60 +127,196 @@ impl SelectText {
                         "".to_string()
                     }
                 };
-                let ft = filter(&t);
+                let ft = SelectText::filter(&t);
                 ft
             }
         }
     }
 }
 
+/// Language names and codes of google
+const GOOGLE_LANGS: &[(&str, &str)] = &[
+    ("English", "en"),
+    ("Chinese (Simplified)", "zh-CN"),
+    ("Chinese (Traditional)", "zh-TW"),
+    ("Japanese", "ja"),
+    ("Korean", "ko"),
+    ("French", "fr"),
+    ("Russian", "ru"),
+    ("German", "de"),
+    ("Spanish", "es"),
+    ("Italian", "it"),
+];
+
+/// Language names and codes of google cloud, the source language can be detected
+const GOOGLECLOUD_LANGS: &[(&str, &str)] = &[
+    ("Auto", "auto"),
+    ("English", "en"),
+    ("Chinese (Simplified)", "zh-CN"),
+    ("Chinese (Traditional)", "zh-TW"),
+    ("Japanese", "ja"),
+    ("Korean", "ko"),
+    ("French", "fr"),
+    ("Russian", "ru"),
+    ("German", "de"),
+    ("Spanish", "es"),
+    ("Italian", "it"),
+];
+
+/// Language names and codes of deepl
+const DEEPL_LANGS: &[(&str, &str)] = &[
+    ("English", "EN"),
+    ("Chinese", "ZH"),
+    ("Japanese", "JA"),
+    ("French", "FR"),
+    ("German", "DE"),
+    ("Korean", "KO"),
+    ("Russian", "RU"),
+    ("Spanish", "ES"),
+    ("Italian", "IT"),
+    ("English (American)", "EN-US"),
+    ("English (British)", "EN-GB"),
+    ("Chinese (Simplified)", "ZH"),
+];
+
+/// Language names and codes of libretranslate
+const LIBRETRANSLATE_LANGS: &[(&str, &str)] = &[
+    ("Auto", "auto"),
+    ("English", "en"),
+    ("Chinese (Simplified)", "zh"),
+    ("Chinese (Traditional)", "zt"),
+    ("Japanese", "ja"),
+    ("Korean", "ko"),
+    ("French", "fr"),
+    ("Russian", "ru"),
+    ("German", "de"),
+    ("Spanish", "es"),
+    ("Italian", "it"),
+];
+
+/// Language names and codes of azure
+const AZURE_LANGS: &[(&str, &str)] = &[
+    ("Auto", "auto"),
+    ("English", "en"),
+    ("Chinese (Simplified)", "zh-Hans"),
+    ("Chinese (Traditional)", "zh-Hant"),
+    ("Japanese", "ja"),
+    ("Korean", "ko"),
+    ("French", "fr"),
+    ("Russian", "ru"),
+    ("German", "de"),
+    ("Spanish", "es"),
+    ("Italian", "it"),
+];
+
+/// Language names and codes of baidu, which are not iso 639
+const BAIDU_LANGS: &[(&str, &str)] = &[
+    ("Auto", "auto"),
+    ("English", "en"),
+    ("Chinese (Simplified)", "zh"),
+    ("Chinese (Traditional)", "cht"),
+    ("Japanese", "jp"),
+    ("Korean", "kor"),
+    ("French", "fra"),
+    ("Russian", "ru"),
+    ("German", "de"),
+    ("Spanish", "spa"),
+    ("Italian", "it"),
+];
+
+/// Language names and codes of youdao
+const YOUDAO_LANGS: &[(&str, &str)] = &[
+    ("Auto", "auto"),
+    ("English", "en"),
+    ("Chinese (Simplified)", "zh-CHS"),
+    ("Chinese (Traditional)", "zh-CHT"),
+    ("Japanese", "ja"),
+    ("Korean", "ko"),
+    ("French", "fr"),
+    ("Russian", "ru"),
+    ("German", "de"),
+    ("Spanish", "es"),
+    ("Italian", "it"),
+];
+
+/// Language names and codes of aws
+const AWS_LANGS: &[(&str, &str)] = &[
+    ("Auto", "auto"),
+    ("English", "en"),
+    ("Chinese (Simplified)", "zh"),
+    ("Chinese (Traditional)", "zh-TW"),
+    ("Japanese", "ja"),
+    ("Korean", "ko"),
+    ("French", "fr"),
+    ("Russian", "ru"),
+    ("German", "de"),
+    ("Spanish", "es"),
+    ("Italian", "it"),
+];
+
+/// Other spellings of the codes, `-t zh` is simplified chinese for every provider
+fn lang_aliases(api: &str) -> &'static [(&'static str, &'static str)] {
+    match api {
+        "google" | "googlecloud" | "llm" | "exec" | "echo" | "pseudo" => &[("zh", "zh-CN")],
+        "deepl" | "deeplpro" => &[("zh-CN", "ZH")],
+        "azure" => &[
+            ("zh", "zh-Hans"),
+            ("zh-CN", "zh-Hans"),
+            ("zh-TW", "zh-Hant"),
+        ],
+        "youdao" => &[("zh", "zh-CHS"), ("zh-CN", "zh-CHS"), ("zh-TW", "zh-CHT")],
+        "libretranslate" | "baidu" | "aws" => &[("zh-CN", "zh")],
+        _ => &[],
+    }
+}
+
+/// `(name, code)` pairs of the languages
+type LangTable = &'static [(&'static str, &'static str)];
+
+/// The language table and the fallback code of the api provider,
+/// a language which is not in the table is passed through when there is no fallback
+pub fn supported_langs(api: &str) -> Result<(LangTable, Option<&'static str>)> {
+    // the tables only hold the common languages, the api checks the other codes
+    match api {
+        "google" => Ok((GOOGLE_LANGS, None)),
+        "googlecloud" => Ok((GOOGLECLOUD_LANGS, None)),
+        "deepl" | "deeplpro" => Ok((DEEPL_LANGS, Some("EN-US"))),
+        "libretranslate" => Ok((LIBRETRANSLATE_LANGS, None)),
+        "azure" => Ok((AZURE_LANGS, None)),
+        "baidu" => Ok((BAIDU_LANGS, None)),
+        "youdao" => Ok((YOUDAO_LANGS, None)),
+        "aws" => Ok((AWS_LANGS, None)),
+        // the external command and the offline providers get the iso 639 codes,
+        // the command may know more languages than the table
+        "exec" | "echo" | "pseudo" => Ok((GOOGLECLOUD_LANGS, None)),
+        // the llm prompt uses the language names of google, the model knows the others
+        "llm" => Ok((GOOGLE_LANGS, None)),
+        _ => Err(UnsupportApiError.into()),
+    }
+}
+
 pub fn standardized_lang<'a>(
     sl: &'a str,  // source language
     tl: &'a str,  // target language
     api: &'a str, // api privoder
 ) -> Result<(&'a str, &'a str)> {
-    let convert = match api {
-        "google" => {
-            let converter = |x: &str| -> &str {
-                let result = match x {
-                    "English" => "en",
-                    "Chinese (Simplified)" => "zh-CN",
-                    "Chinese (Traditional)" => "zh-TW",
-                    "Japanese" => "ja",
-                    "Korean" => "ko",
-                    "French" => "fr",
-                    "Russian" => "ru",
-                    "German" => "de",
-                    "Spanish" => "es",
-                    "Italian" => "it",
-                    _ => "en",
-                };
-                result
-            };
-            converter
-        }
-        "deepl" | "deeplpro" => {
-            let convert_language = |x: &str| -> &str {
-                let result = match x {
-                    "English" => "EN",
-                    "Chinese" => "ZH",
-                    "Japanese" => "JA",
-                    "French" => "FR",
-                    "German" => "DE",
-                    "Korean" => "KO",
-                    "Russian" => "RU",
-                    "Spanish" => "ES",
-                    "Italian" => "IT",
-                    "English (American)" => "EN-US",
-                    "English (British)" => "EN-GB",
-                    "Chinese (Simplified)" => "ZH",
-                    _ => "EN-US",
-                };
-                result
-            };
-            convert_language
+    let (langs, default) = supported_langs(api)?;
+    let aliases = lang_aliases(api);
+    // the language name, the code and the aliases are accepted
+    let convert = |x: &'a str| -> &'a str {
+        let found = langs
+            .iter()
+            .find(|(name, code)| *name == x || code.eq_ignore_ascii_case(x))
+            .or_else(|| {
+                aliases
+                    .iter()
+                    .find(|(alias, _)| alias.eq_ignore_ascii_case(x))
+            });
+        match (found, default) {
+            (Some((_, code)), _) => code,
+            (None, Some(default)) => default,
+            (None, None) => x,
         }
-        _ => return Err(UnsupportApiError.into()),
     };
     let sl_ret = convert(sl);
     let tl_ret = convert(tl);
@@ -201,6 +351,7 @@ pub fn fliter_short(input: &str) -> String {
         .replace("》", "")
 }
 
+#[allow(clippy::let_and_return)]
 pub fn build_proxy(proxy_str: &str) -> Option<Proxy> {
     let proxy = match proxy_str {
         "null" => None,
@@ -208,3 +359,123 @@ pub fn build_proxy(proxy_str: &str) -> Option<Proxy> {
     };
     proxy
 }
+
+pub fn build_client(proxy_str: &str) -> reqwest::Client {
+    let proxy = build_proxy(proxy_str);
+    match proxy {
+        Some(p) => reqwest::Client::builder()
+            .proxy(p)
+            .build()
+            .expect("proxy client build failed"),
+        _ => reqwest::Client::new(),
+    }
+}
+
+/// `~/.config/translator-rs`, `%APPDATA%` on windows
+pub fn config_dir() -> Option<PathBuf> {
+    let dir = match std::env::var("XDG_CONFIG_HOME").or(std::env::var("APPDATA")) {
+        Ok(d) => PathBuf::from(d),
+        Err(_) => PathBuf::from(std::env::var("HOME").ok()?).join(".config"),
+    };
+    Some(dir.join("translator-rs"))
+}
+
+/// The options of an args file, one option per line (`--api=deepl`),
+/// the lines starting with `#` are skipped
+pub fn read_args_file(path: &Path) -> Option<Vec<String>> {
+    let content = std::fs::read_to_string(path).ok()?;
+    Some(
+        content
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(String::from)
+            .collect(),
+    )
+}
+
+/// Find the printf (`%s`, `%1$d`, `%.2f`) and brace (`{name}`, `{0}`) placeholders,
+/// the returned list is sorted so two texts can be compared directly
+pub fn extract_placeholders(input: &str) -> Vec<String> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut placeholders = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '%' if i + 1 < chars.len() && chars[i + 1] == '%' => i += 2,
+            '%' => {
+                // %[argnum$][flags][width][.precision][length]conversion
+                let mut j = i + 1;
+                while j < chars.len() && (chars[j].is_ascii_digit() || "$-+#0.".contains(chars[j]))
+                {
+                    j += 1;
+                }
+                while j < chars.len() && "hlLqjzt".contains(chars[j]) {
+                    j += 1;
+                }
+                if j < chars.len() && "diouxXeEfFgGaAcspn@".contains(chars[j]) {
+                    placeholders.push(chars[i..=j].iter().collect());
+                    i = j + 1;
+                } else {
+                    i += 1;
+                }
+            }
+            '{' => match chars[i + 1..].iter().position(|&c| c == '}' || c == '{') {
+                Some(p) if chars[i + 1 + p] == '}' => {
+                    let name: String = chars[i + 1..i + 1 + p].iter().collect();
+                    if !name.is_empty()
+                        && name
+                            .chars()
+                            .all(|c| c.is_alphanumeric() || c == '_' || c == '.')
+                    {
+                        placeholders.push(format!("{{{}}}", name));
+                    }
+                    i += p + 2;
+                }
+                _ => i += 1,
+            },
+            _ => i += 1,
+        }
+    }
+    placeholders.sort();
+    placeholders
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_standardized_lang() {
+        assert_eq!(
+            standardized_lang("English", "zh", "google").unwrap(),
+            ("en", "zh-CN")
+        );
+        assert_eq!(
+            standardized_lang("en", "zh-CN", "deepl").unwrap(),
+            ("EN", "ZH")
+        );
+        assert_eq!(
+            standardized_lang("English", "zh", "azure").unwrap(),
+            ("en", "zh-Hans")
+        );
+        assert!(standardized_lang("en", "zh", "nothing").is_err());
+        // the exec command gets the codes it may know
+        assert_eq!(
+            standardized_lang("English", "pt-BR", "exec").unwrap(),
+            ("en", "pt-BR")
+        );
+        assert_eq!(
+            standardized_lang("Auto", "pt-br", "azure").unwrap(),
+            ("auto", "pt-br")
+        );
+        assert_eq!(
+            standardized_lang("en", "pt-BR", "googlecloud").unwrap(),
+            ("en", "pt-BR")
+        );
+        assert_eq!(
+            standardized_lang("English", "Brazilian Portuguese", "llm").unwrap(),
+            ("en", "Brazilian Portuguese")
+        );
+    }
+}
@@ -0,0 +1,316 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+use std::time::SystemTime;
+
+use crate::errors::YoudaoEmptyAuthKeyError;
+use crate::errors::YoudaoError;
+use crate::utils::{build_client, SelectText};
+use crate::Dictionary;
+use crate::Phonetic;
+use crate::TranslateResult;
+use crate::WebPhrase;
+use crate::TIMEOUT;
+
+const YOUDAO_URL: &str = "https://openapi.youdao.com/api";
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct YoudaoBasic {
+    pub phonetic: Option<String>,
+    #[serde(rename = "us-phonetic")]
+    pub us_phonetic: Option<String>,
+    #[serde(rename = "uk-phonetic")]
+    pub uk_phonetic: Option<String>,
+    #[serde(default)]
+    pub explains: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct YoudaoWeb {
+    pub key: String,
+    #[serde(default)]
+    pub value: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct YoudaoResponse {
+    pub error_code: String,
+    #[serde(default)]
+    pub translation: Vec<String>,
+    pub basic: Option<YoudaoBasic>,
+    pub web: Option<Vec<YoudaoWeb>>,
+}
+
+/// The input of the v3 sign, a long query is truncated to the first 10 chars,
+/// the length and the last 10 chars
+pub fn sign_input(q: &str) -> String {
+    let chars: Vec<char> = q.chars().collect();
+    let len = chars.len();
+    match len > 20 {
+        true => format!(
+            "{}{}{}",
+            chars[..10].iter().collect::<String>(),
+            len,
+            chars[len - 10..].iter().collect::<String>()
+        ),
+        false => q.to_string(),
+    }
+}
+
+/// `sign = sha256(appKey + input + salt + curtime + appSecret)`
+pub fn sign(app_key: &str, q: &str, salt: &str, curtime: &str, app_secret: &str) -> String {
+    let digest = Sha256::digest(
+        format!(
+            "{}{}{}{}{}",
+            app_key,
+            sign_input(q),
+            salt,
+            curtime,
+            app_secret
+        )
+        .as_bytes(),
+    );
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Describe the common error codes, the full list is in the youdao document
+fn error_message(code: &str) -> &str {
+    match code {
+        "101" => "missing required parameter",
+        "102" => "unsupported language",
+        "108" => "invalid app key",
+        "202" => "sign check failed",
+        "401" => "the account is overdue",
+        "411" => "too many requests, please slow down",
+        _ => "unknown error",
+    }
+}
+
+fn dictionary(basic: YoudaoBasic, web: Vec<YoudaoWeb>) -> Dictionary {
+    let mut phonetic = Vec::new();
+    for (accent, symbol) in [
+        ("us", basic.us_phonetic),
+        ("uk", basic.uk_phonetic),
+        ("", basic.phonetic),
+    ] {
+        // the general one is only shown when there is no accent
+        let shown = accent.is_empty() && !phonetic.is_empty();
+        if let Some(s) = symbol.filter(|s| !s.is_empty() && !shown) {
+            phonetic.push(Phonetic {
+                accent: accent.to_string(),
+                symbol: s,
+            });
+        }
+    }
+    Dictionary {
+        phonetic,
+        explains: basic.explains,
+        web: web
+            .into_iter()
+            .map(|w| WebPhrase {
+                key: w.key,
+                value: w.value,
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+async fn request(
+    sl: &str,
+    tl: &str,
+    q: &str,
+    proxy_str: &str,
+    app_key: &str,
+    app_secret: &str,
+    endpoint: &str,
+) -> Result<YoudaoResponse> {
+    if app_key == "null" || app_secret == "null" || app_key.is_empty() || app_secret.is_empty() {
+        return Err(YoudaoEmptyAuthKeyError.into());
+    }
+    let endpoint = match endpoint {
+        "null" => YOUDAO_URL,
+        _ => endpoint,
+    };
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    let salt = now.as_millis().to_string();
+    let curtime = now.as_secs().to_string();
+    let sign = sign(app_key, q, &salt, &curtime, app_secret);
+    let params = [
+        ("q", q),
+        ("from", sl),
+        ("to", tl),
+        ("appKey", app_key),
+        ("salt", &salt),
+        ("sign", &sign),
+        ("signType", "v3"),
+        ("curtime", &curtime),
+    ];
+
+    let client = build_client(proxy_str);
+    let res = client
+        .post(endpoint)
+        .form(&params)
+        .timeout(Duration::from_secs(TIMEOUT))
+        .send()
+        .await?
+        .json::<YoudaoResponse>()
+        .await?;
+    match res.error_code.as_str() {
+        "0" => Ok(res),
+        code => Err(YoudaoError(format!("{} ({})", error_message(code), code)).into()),
+    }
+}
+
+pub async fn translate_youdao(
+    sl: &str, // source language
+    tl: &str, // target language
+    content: &str,
+    proxy_str: &str,
+    app_key: &str,
+    app_secret: &str,
+    endpoint: &str,
+) -> Result<Vec<TranslateResult>> {
+    let content = SelectText::unescape(content);
+    let res = request(sl, tl, &content, proxy_str, app_key, app_secret, endpoint).await?;
+
+    // a single word gets the dictionary explanations and the web phrases, youdao has no alternatives
+    let web = res.web.unwrap_or_default();
+    let dict = res.basic.map(|basic| dictionary(basic, web));
+    let item = TranslateResult {
+        orig: content,
+        trans: res.translation.join("\n"),
+        alter: Vec::new(),
+        dict,
+        romanization: None,
+        correction: None,
+        detected_lang: None,
+    };
+    Ok(vec![item])
+}
+
+pub async fn translate_batch_youdao(
+    sl: &str, // source language
+    tl: &str, // target language
+    texts: &[String],
+    proxy_str: &str,
+    app_key: &str,
+    app_secret: &str,
+    endpoint: &str,
+) -> Result<Vec<String>> {
+    let mut result_vec = Vec::new();
+    for t in texts {
+        // youdao rejects an empty query
+        if t.trim().is_empty() {
+            result_vec.push(String::new());
+            continue;
+        }
+        let res = request(sl, tl, t, proxy_str, app_key, app_secret, endpoint).await?;
+        result_vec.push(res.translation.join("\n"));
+    }
+    Ok(result_vec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::HttpResponse;
+    use crate::test_support::mock_server;
+    use serde_json::json;
+
+    #[test]
+    fn test_sign() {
+        assert_eq!(sign_input("hello"), "hello");
+        assert_eq!(
+            sign_input("abcdefghijklmnopqrstuvwxyz"),
+            "abcdefghij26qrstuvwxyz"
+        );
+        // the chars are counted, not the bytes
+        assert_eq!(
+            sign_input("一二三四五六七八九十甲乙丙丁戊己庚辛壬癸子"),
+            "一二三四五六七八九十21乙丙丁戊己庚辛壬癸子"
+        );
+        assert_eq!(
+            sign("appkey", "hello", "salt", "1700000000", "secret"),
+            "410d75111b6c4ce44e3293b69c0f6f84d905f5c1a5dc8fbdd8cc143672523f42"
+        );
+    }
+
+    async fn youdao_server() -> String {
+        mock_server(|request| {
+            let param = |name: &str| request.form_param(name);
+            let q = param("q");
+            let expected = sign(
+                &param("appKey"),
+                &q,
+                &param("salt"),
+                &param("curtime"),
+                "secret",
+            );
+            if param("signType") != "v3" || param("sign") != expected {
+                return HttpResponse::json(200, json!({ "errorCode": "202" }));
+            }
+            match q.as_str() {
+                "apple" => HttpResponse::json(
+                    200,
+                    json!({
+                        "errorCode": "0",
+                        "query": "apple",
+                        "translation": ["苹果"],
+                        "basic": {
+                            "phonetic": "ˈæpl",
+                            "us-phonetic": "ˈæpl",
+                            "uk-phonetic": "ˈæpl",
+                            "explains": ["n. 苹果", "n. 苹果树"]
+                        },
+                        "web": [
+                            { "key": "apple pie", "value": ["苹果派", "苹果馅饼"] }
+                        ]
+                    }),
+                ),
+                _ => HttpResponse::json(
+                    200,
+                    json!({ "errorCode": "0", "translation": [format!("{}:{}", param("to"), q)] }),
+                ),
+            }
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_translate_youdao() {
+        let url = youdao_server().await;
+        let r = translate_youdao("en", "zh-CHS", "apple", "null", "appkey", "secret", &url)
+            .await
+            .unwrap();
+        assert_eq!(r[0].trans, "苹果");
+        // the explanations and the web phrases are shown once, in the dictionary
+        assert!(r[0].alter.is_empty());
+        let dict = r[0].dict.as_ref().unwrap();
+        assert_eq!(dict.explains, vec!["n. 苹果", "n. 苹果树"]);
+        assert_eq!(dict.phonetic_text(), "us /ˈæpl/ uk /ˈæpl/");
+        assert_eq!(dict.web[0].key, "apple pie");
+
+        let long = "a long sentence which is longer than twenty chars";
+        let r = translate_youdao("en", "ja", long, "null", "appkey", "secret", &url)
+            .await
+            .unwrap();
+        assert_eq!(r[0].trans, format!("ja:{}", long));
+        assert!(r[0].dict.is_none());
+
+        let texts = vec!["a".to_string(), " ".to_string()];
+        let r = translate_batch_youdao("en", "ja", &texts, "null", "appkey", "secret", &url)
+            .await
+            .unwrap();
+        assert_eq!(r, vec!["ja:a", ""]);
+
+        let e = translate_youdao("en", "ja", "word", "null", "appkey", "wrong", &url)
+            .await
+            .unwrap_err();
+        assert_eq!(e.to_string(), "youdao error: sign check failed (202)");
+    }
+}